@@ -0,0 +1,104 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use wit_derive_codegen::{error, Opts};
+
+#[derive(Parser)]
+#[command(name = "wit-derive", about = "Generate host bindings for WIT worlds")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write the bindings `wit_derive::generate!` would expand to into a file.
+    #[command(after_help = "The options are documented on `wit_derive_codegen::Opts`.")]
+    Generate(GenerateArgs),
+}
+
+#[derive(clap::Args)]
+struct GenerateArgs {
+    /// The world to generate bindings for, by name or as `namespace:package/world@version`.
+    /// Can be left out when the main package defines a single world.
+    #[arg(long)]
+    world: Option<String>,
+    /// Directories, `.wit` files or `.wasm` encoded packages. The first one holds the world,
+    /// the others are resolved beforehand as dependencies.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+    /// Output file, the bindings are printed to stdout if omitted.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Generate recording stubs of the imports.
+    #[arg(long)]
+    stubs: bool,
+    /// Make the fields of `Imports` optional.
+    #[arg(long)]
+    optional_imports: bool,
+    /// Make import and export methods async.
+    #[arg(long = "async")]
+    async_mode: bool,
+    /// Share state through `Rc<RefCell<..>>` with `single`.
+    #[arg(long, value_enum, default_value_t = Threading::Multi)]
+    threading: Threading,
+    /// Generate `stub_component` for testing hosts.
+    #[arg(long)]
+    stub_component: bool,
+    /// Generate `wit::resolve()`.
+    #[arg(long)]
+    resolve: bool,
+    /// Run calls in `tracing` spans.
+    #[arg(long)]
+    tracing: bool,
+    /// `include_str!` the WIT files by absolute path instead of embedding their content.
+    #[arg(long)]
+    include_sources: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Threading {
+    Single,
+    Multi,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", error::render(&e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let Command::Generate(args) = cli.command;
+
+    // Checked in bindings are built elsewhere, so the WIT files are embedded by default
+    let mut opts = Opts::new();
+    if let Some(world) = args.world {
+        opts = opts.world(world);
+    }
+    let opts = args
+        .paths
+        .iter()
+        .fold(opts, |opts, path| opts.path(path))
+        .stubs(args.stubs)
+        .optional_imports(args.optional_imports)
+        .async_mode(args.async_mode)
+        .single_threaded(args.threading == Threading::Single)
+        .stub_component(args.stub_component)
+        .resolve(args.resolve)
+        .tracing(args.tracing)
+        .embed_sources(!args.include_sources);
+
+    match args.out {
+        Some(out) => opts.generate_to_file(out),
+        None => {
+            print!("{}", opts.generate_string()?);
+            Ok(())
+        }
+    }
+}
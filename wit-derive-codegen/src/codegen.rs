@@ -0,0 +1,3114 @@
+use anyhow::{Context, Result};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::path::PathBuf;
+use wit_parser::{
+    Function, Interface, Package, Resolve, Type, TypeDefKind, World, WorldItem, WorldKey,
+};
+
+pub struct CodeGenerator<'a> {
+    resolve: &'a Resolve,
+    package: &'a Package,
+    world: &'a World,
+    wit_source: WitSourceContent,
+    options: Options,
+}
+
+/// Switches for optional parts of the generated bindings, documented on the [`Opts`](crate::Opts)
+/// methods of the same name.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub stubs: bool,
+    pub optional_imports: bool,
+    pub async_mode: bool,
+    pub single_threaded: bool,
+    pub embed_sources: bool,
+    pub stub_component: bool,
+    pub resolve: bool,
+    pub tracing: bool,
+}
+
+pub struct WitSourceContent {
+    pub files: Option<Vec<(String, PathBuf)>>, // Vec<(relative path, absolute path)>
+    pub inline: Option<String>,
+}
+
+impl<'a> CodeGenerator<'a> {
+    pub fn new(
+        resolve: &'a Resolve,
+        package: &'a Package,
+        world: &'a World,
+        wit_source: WitSourceContent,
+        options: Options,
+    ) -> Self {
+        Self {
+            resolve,
+            package,
+            world,
+            wit_source,
+            options,
+        }
+    }
+
+    pub fn generate(&mut self) -> Result<TokenStream> {
+        let package_name = self.package.name.name.replace('-', "_");
+        let mod_name = format_ident!("{}", package_name);
+
+        let mut import_traits = Vec::new();
+        let mut export_traits = Vec::new();
+        let mut import_impls = Vec::new();
+        let mut export_impls = Vec::new();
+        let mut import_fields = Vec::new();
+        let mut export_fields = Vec::new();
+        let mut export_names = Vec::new();
+
+        // Process imports
+        for (key, item) in &self.world.imports {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    let interface = &self.resolve.interfaces[*id];
+                    let (trait_def, impl_def, field_name) = self
+                        .generate_import_interface(key, interface)
+                        .with_context(|| {
+                            format!("In imported interface '{}'", self.get_interface_name(key))
+                        })?;
+                    import_traits.push(trait_def);
+                    import_impls.push(impl_def);
+                    import_fields.push(field_name);
+                }
+                WorldItem::Function(_) => {
+                    // Handle top-level functions if needed
+                }
+                WorldItem::Type(_) => {
+                    // Handle top-level types if needed
+                }
+            }
+        }
+
+        // Process exports
+        for (key, item) in &self.world.exports {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    let interface = &self.resolve.interfaces[*id];
+                    let (trait_def, impl_def, field_name) = self
+                        .generate_export_interface(key, interface)
+                        .with_context(|| {
+                            format!("In exported interface '{}'", self.get_interface_name(key))
+                        })?;
+                    export_traits.push(trait_def);
+                    export_impls.push(impl_def);
+                    export_fields.push(field_name);
+                    export_names.push(format_ident!(
+                        "{}",
+                        self.get_interface_name(key).to_lowercase()
+                    ));
+                }
+                WorldItem::Function(_) => {
+                    // Handle top-level functions if needed
+                }
+                WorldItem::Type(_) => {
+                    // Handle top-level types if needed
+                }
+            }
+        }
+
+        let instantiate_fn = self.generate_instantiate_function(&import_fields, &export_fields)?;
+        let wit_module = self.generate_wit_module()?;
+        let compatibility_check = self.generate_compatibility_check()?;
+        let imports_builder = self.generate_imports_builder()?;
+        let stub_component = if self.options.stub_component {
+            self.generate_stub_component()
+        } else {
+            quote! {}
+        };
+        let shared_imports = if self.options.single_threaded {
+            quote! {}
+        } else {
+            quote! { use std::sync::{Arc, Mutex}; }
+        };
+        let store_type = self.shared_type(quote! { Store<T, E> });
+        let lock_store_arg = self.shared_lock(quote! { store });
+        let (forwarder_send, forwarder_bounds) = if self.options.single_threaded {
+            (quote! {}, quote! {})
+        } else {
+            (quote! { + Send }, quote! { Store<T, E>: Send, })
+        };
+        let (store_guard, store_guard_mut) = if self.options.single_threaded {
+            (
+                quote! { std::cell::Ref<'_, Store<T, E>> },
+                quote! { std::cell::RefMut<'_, Store<T, E>> },
+            )
+        } else {
+            (
+                quote! { std::sync::MutexGuard<'_, Store<T, E>> },
+                quote! { std::sync::MutexGuard<'_, Store<T, E>> },
+            )
+        };
+        let (lock_store, lock_store_mut, unwrap_store) = if self.options.single_threaded {
+            (
+                quote! { self.store.borrow() },
+                quote! { self.store.borrow_mut() },
+                quote! {
+                    std::rc::Rc::try_unwrap(store)
+                        .map_err(|_| anyhow::anyhow!("store is still shared"))?
+                        .into_inner()
+                },
+            )
+        } else {
+            (
+                // A trapping export poisons the mutex, the store stays usable regardless
+                quote! { self.store.lock().unwrap_or_else(std::sync::PoisonError::into_inner) },
+                quote! { self.store.lock().unwrap_or_else(std::sync::PoisonError::into_inner) },
+                quote! {
+                    std::sync::Arc::try_unwrap(store)
+                        .map_err(|_| anyhow::anyhow!("store is still shared"))?
+                        .into_inner()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                },
+            )
+        };
+        let async_bounds = self.async_bounds();
+        let (dynamic_calls, call_export_blocking) = if self.options.async_mode {
+            (
+                quote! {
+                    /// Calls an export by name with untyped arguments.
+                    ///
+                    /// `interface` is either the plain interface name or its fully qualified
+                    /// identifier. The arguments are checked against the types compiled into the
+                    /// bindings before the call, which runs on tokio's blocking pool like the
+                    /// typed export methods.
+                    pub fn call_dynamic(
+                        &mut self,
+                        interface: &str,
+                        func: &str,
+                        args: &[Value],
+                    ) -> BoxFuture<'static, anyhow::Result<Vec<Value>>>
+                    where
+                        #async_bounds
+                    {
+                        call_export_blocking(self.store.clone(), self.instance.clone(), interface, func, args)
+                    }
+
+                    /// Turns these exports into a function that calls them dynamically, to
+                    /// implement the imports of another world through its `*Forwarder` types.
+                    /// The instance stays alive as long as the function does.
+                    pub fn forwarder(
+                        &self,
+                    ) -> impl FnMut(&str, &str, &[Value]) -> BoxFuture<'static, anyhow::Result<Vec<Value>>> + Clone + Send + 'static
+                    where
+                        #async_bounds
+                    {
+                        let store = self.store.clone();
+                        let instance = self.instance.clone();
+                        move |interface: &str, func: &str, args: &[Value]| {
+                            call_export_blocking(store.clone(), instance.clone(), interface, func, args)
+                        }
+                    }
+                },
+                quote! {
+                    /// Runs [`call_export`] on tokio's blocking pool, where imports may block on
+                    /// their futures.
+                    fn call_export_blocking<T, E>(
+                        store: #store_type,
+                        instance: wasm_component_layer::Instance,
+                        interface: &str,
+                        func: &str,
+                        args: &[Value],
+                    ) -> BoxFuture<'static, anyhow::Result<Vec<Value>>>
+                    where
+                        E: wasm_runtime_layer::backend::WasmEngine,
+                        #async_bounds
+                    {
+                        let interface = interface.to_string();
+                        let func = func.to_string();
+                        let args = args.to_vec();
+                        Box::pin(async move {
+                            tokio::task::spawn_blocking(move || {
+                                call_export(&store, &instance, &interface, &func, &args)
+                            })
+                            .await?
+                        })
+                    }
+                },
+            )
+        } else {
+            (
+                quote! {
+                    /// Calls an export by name with untyped arguments.
+                    ///
+                    /// `interface` is either the plain interface name or its fully qualified
+                    /// identifier. The arguments are checked against the types compiled into the
+                    /// bindings before the call.
+                    pub fn call_dynamic(
+                        &mut self,
+                        interface: &str,
+                        func: &str,
+                        args: &[Value],
+                    ) -> anyhow::Result<Vec<Value>> {
+                        call_export(&self.store, &self.instance, interface, func, args)
+                    }
+
+                    /// Turns these exports into a function that calls them dynamically, to
+                    /// implement the imports of another world through its `*Forwarder` types.
+                    /// The instance stays alive as long as the function does.
+                    pub fn forwarder(
+                        &self,
+                    ) -> impl FnMut(&str, &str, &[Value]) -> anyhow::Result<Vec<Value>> + Clone #forwarder_send + 'static
+                    where
+                        T: 'static,
+                        #forwarder_bounds
+                    {
+                        let store = self.store.clone();
+                        let instance = self.instance.clone();
+                        move |interface: &str, func: &str, args: &[Value]| {
+                            call_export(&store, &instance, interface, func, args)
+                        }
+                    }
+                },
+                quote! {},
+            )
+        };
+        let box_future = if self.options.async_mode {
+            quote! {
+                /// The future returned by import and export methods.
+                pub type BoxFuture<'a, T> =
+                    std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+            }
+        } else {
+            quote! {}
+        };
+
+        let result = quote! {
+            pub mod #mod_name {
+                #shared_imports
+                use wasm_component_layer::{
+                    self,
+                    Component,
+                    Func,
+                    FuncType,
+                    InterfaceIdentifier,
+                    Linker,
+                    PackageIdentifier,
+                    PackageName,
+                    Store,
+                    TypedFunc,
+                    Value,
+                    ValueType,
+                };
+                use wasm_runtime_layer::backend::WasmEngine;
+                use anyhow::Result;
+
+                #wit_module
+
+                #box_future
+
+                #(#import_traits)*
+                #(#export_traits)*
+
+                pub struct Imports {
+                    #(#import_fields,)*
+                }
+
+                #imports_builder
+
+                pub struct Exports<T, E>
+                where
+                    E: wasm_runtime_layer::backend::WasmEngine,
+                {
+                    #(#export_fields),*,
+                    // Shared with the export implementations, which need it for every call
+                    store: #store_type,
+                    instance: wasm_component_layer::Instance,
+                    instance_slot: InstanceSlot,
+                }
+
+                impl<T, E> Exports<T, E>
+                where
+                    E: wasm_runtime_layer::backend::WasmEngine,
+                {
+                    /// Locks the store the component was instantiated in.
+                    pub fn store(&self) -> #store_guard {
+                        #lock_store
+                    }
+
+                    /// Locks the store the component was instantiated in, for changing its data.
+                    ///
+                    /// The guard must be dropped before calling an export again, as the calls
+                    /// lock the same store.
+                    pub fn store_mut(&mut self) -> #store_guard_mut {
+                        #lock_store_mut
+                    }
+
+                    /// The raw instance, for exports the bindings don't cover.
+                    pub fn instance(&self) -> &wasm_component_layer::Instance {
+                        &self.instance
+                    }
+
+                    #dynamic_calls
+
+                    /// Drops the instance and hands back its store.
+                    ///
+                    /// Errors raised by resource destructors while dropping the instance are
+                    /// returned instead of the store, several of them as one error listing each.
+                    pub fn into_store(self) -> anyhow::Result<Store<T, E>> {
+                        let Self { store, instance, instance_slot: _instance_slot, #(#export_names),* } = self;
+                        // Dropping the export implementations leaves us holding the only
+                        // reference to the store
+                        #(drop(#export_names);)*
+                        let mut store = #unwrap_store;
+                        let mut errors = instance.drop(&mut store)?;
+                        if errors.len() > 1 {
+                            let messages: Vec<String> =
+                                errors.iter().map(|error| format!("{:#}", error)).collect();
+                            anyhow::bail!(
+                                "{} resource destructors failed:\n{}",
+                                errors.len(),
+                                messages.join("\n")
+                            );
+                        }
+                        match errors.pop() {
+                            Some(error) => Err(error),
+                            None => Ok(store),
+                        }
+                    }
+                }
+
+                #call_export_blocking
+
+                /// Shared by [`Exports::call_dynamic`] and [`Exports::forwarder`].
+                fn call_export<T, E>(
+                    store: &#store_type,
+                    instance: &wasm_component_layer::Instance,
+                    interface: &str,
+                    func: &str,
+                    args: &[Value],
+                ) -> anyhow::Result<Vec<Value>>
+                where
+                    E: wasm_runtime_layer::backend::WasmEngine,
+                {
+                    use anyhow::Context;
+
+                    let signature = world_exports()
+                        .iter()
+                        .find(|signature| signature.matches(interface))
+                        .with_context(|| format!("The world does not export `{}`", interface))?;
+                    let function = signature
+                        .function(func)
+                        .with_context(|| {
+                            format!("`{}` has no function `{}`", signature.qualified_name(), func)
+                        })?;
+
+                    if args.len() != function.params.len() {
+                        anyhow::bail!(
+                            "`{}` expects {} arguments, got {}",
+                            func,
+                            function.params.len(),
+                            args.len()
+                        );
+                    }
+                    for ((name, ty), arg) in function.params.iter().zip(args) {
+                        if arg.ty() != *ty {
+                            anyhow::bail!(
+                                "Argument `{}` of `{}` must be {}, got {}",
+                                name,
+                                func,
+                                wit_value_type(ty),
+                                wit_value_type(&arg.ty())
+                            );
+                        }
+                    }
+
+                    let export = match &signature.id {
+                        Some(id) => {
+                            let interface_id = compatible_export(instance.component(), id);
+                            instance
+                                .exports()
+                                .instance(&interface_id)
+                                .with_context(|| format!("Component does not export `{}`", interface_id))?
+                                .func(func)
+                                .with_context(|| format!("`{}` does not export `{}`", interface_id, func))?
+                        }
+                        None => instance
+                            .exports()
+                            .root()
+                            .func(func)
+                            .with_context(|| format!("Component does not export `{}`", func))?,
+                    };
+
+                    // Placeholders, overwritten by the call
+                    let mut results = vec![Value::Bool(false); function.results.len()];
+                    let mut store = #lock_store_arg;
+                    export.call(&mut *store, args, &mut results)?;
+                    Ok(results)
+                }
+
+                #(#import_impls)*
+                #(#export_impls)*
+
+                #compatibility_check
+
+                #instantiate_fn
+
+                #stub_component
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn generate_wit_module(&self) -> Result<TokenStream> {
+        let files_module = match &self.wit_source.files {
+            Some(files) => {
+                // Going through `include_str!`/`include_bytes!` makes cargo rebuild the
+                // bindings whenever one of the WIT sources changes.
+                let mut file_entries = Vec::new();
+                let mut tracked_packages = Vec::new();
+                for (relative_path, path) in files {
+                    if self.options.embed_sources {
+                        if !relative_path.ends_with(".wasm") {
+                            let content = std::fs::read_to_string(path).with_context(|| {
+                                format!("Failed to read WIT file {}", path.display())
+                            })?;
+                            file_entries.push(quote! {
+                                (#relative_path, #content)
+                            });
+                        }
+                        continue;
+                    }
+
+                    let path = path.to_string_lossy();
+                    if relative_path.ends_with(".wasm") {
+                        tracked_packages.push(quote! {
+                            const _: &[u8] = include_bytes!(#path);
+                        });
+                    } else {
+                        file_entries.push(quote! {
+                            (#relative_path, include_str!(#path))
+                        });
+                    }
+                }
+
+                quote! {
+                    pub mod files {
+                        /// Every WIT file the bindings were generated from, as
+                        /// `(relative path, content)` with `/` separated paths.
+                        pub const FILES: &[(&str, &str)] = &[#(#file_entries),*];
+
+                        /// Returns the content of the WIT file at `path`, as listed in [`FILES`].
+                        pub fn lookup(path: &str) -> Option<&'static str> {
+                            let path = path.replace('\\', "/");
+                            FILES
+                                .iter()
+                                .find(|(file, _)| *file == path)
+                                .map(|(_, content)| *content)
+                        }
+
+                        #(#tracked_packages)*
+                    }
+                }
+            }
+            None => quote! {},
+        };
+        let inline_const = match &self.wit_source.inline {
+            Some(content) => quote! {
+                pub const INLINE: &'static str = #content;
+            },
+            None => quote! {},
+        };
+
+        // The package of the world, encoded together with everything it depends on
+        let package_id = self.world.package.context("World does not belong to a package")?;
+        let encoded = wit_component::encode(self.resolve, package_id)
+            .context("Failed to encode the WIT package")?;
+        let encoded = proc_macro2::Literal::byte_string(&encoded);
+        let world_name = &self.world.name;
+        // `stub_component` builds on it
+        let resolve = if self.options.resolve || self.options.stub_component {
+            quote! {
+                /// Decodes [`ENCODED_PACKAGE`], returning the `Resolve` together with the id of
+                /// [`WORLD`] inside it. Needs `wit-parser` as a dependency.
+                pub fn resolve() -> anyhow::Result<(wit_parser::Resolve, wit_parser::WorldId)> {
+                    match wit_parser::decoding::decode(ENCODED_PACKAGE)? {
+                        wit_parser::decoding::DecodedWasm::WitPackage(resolve, package_id) => {
+                            let world_id = resolve.select_world(package_id, Some(WORLD))?;
+                            Ok((resolve, world_id))
+                        }
+                        wit_parser::decoding::DecodedWasm::Component(..) => {
+                            anyhow::bail!("ENCODED_PACKAGE is not a WIT package")
+                        }
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        Ok(quote! {
+            pub mod wit {
+                #files_module
+                #inline_const
+
+                /// Name of the world the bindings were generated for.
+                pub const WORLD: &str = #world_name;
+
+                /// The resolved package of [`WORLD`] and all its dependencies, encoded as a
+                /// binary WIT package.
+                pub const ENCODED_PACKAGE: &[u8] = #encoded;
+
+                #resolve
+            }
+        })
+    }
+
+    /// Generates `stub_component`, which turns the dummy module of `wit_component` into a
+    /// component of the world, with optional bodies for its exports.
+    fn generate_stub_component(&self) -> TokenStream {
+        quote! {
+            /// Builds a component of the world from [`wit::ENCODED_PACKAGE`], so the host can be
+            /// tested without a guest toolchain. Needs `wit-component` with the `dummy-module`
+            /// feature, `wasmparser` and `wasm-encoder`.
+            ///
+            /// Every export traps, unless `bodies` gives its core wasm instructions, keyed by
+            /// `interface#function`. Parameters are flattened to core wasm locals by the canonical
+            /// ABI, and imported functions are numbered in the order the world imports them, e.g.
+            /// `[LocalGet(0), Call(0)]` forwards a `u32` to the first import.
+            pub fn stub_component(
+                bodies: &[(&str, &[wasm_encoder::Instruction<'_>])],
+            ) -> anyhow::Result<Vec<u8>> {
+                use anyhow::Context;
+                use wit_parser::{ManglingAndAbi, WasmExport, WasmExportKind, WorldItem, WorldKey};
+
+                let (resolve, world_id) = wit::resolve()?;
+                let mangling = ManglingAndAbi::Standard32;
+                let mut module = wit_component::dummy_module(&resolve, world_id, mangling);
+
+                if !bodies.is_empty() {
+                    let mut replacements = Vec::new();
+                    for (export, body) in bodies {
+                        let (interface, function) = export.split_once('#').with_context(|| {
+                            format!("`{}` is not written as `interface#function`", export)
+                        })?;
+
+                        // Interfaces are matched by their plain or fully qualified name
+                        let (key, functions) = resolve.worlds[world_id]
+                            .exports
+                            .iter()
+                            .find_map(|(key, item)| {
+                                let WorldItem::Interface { id, .. } = item else {
+                                    return None;
+                                };
+                                let name = match key {
+                                    WorldKey::Name(name) => Some(name.as_str()),
+                                    WorldKey::Interface(id) => resolve.interfaces[*id].name.as_deref(),
+                                };
+                                (name == Some(interface) || resolve.name_world_key(key) == interface)
+                                    .then(|| (key, &resolve.interfaces[*id].functions))
+                            })
+                            .with_context(|| format!("The world does not export `{}`", interface))?;
+                        let func = functions
+                            .get(function)
+                            .with_context(|| format!("`{}` has no function `{}`", interface, function))?;
+
+                        let name = resolve.wasm_export_name(
+                            mangling,
+                            WasmExport::Func {
+                                interface: Some(key),
+                                func,
+                                kind: WasmExportKind::Normal,
+                            },
+                        );
+                        replacements.push((name, *body));
+                    }
+                    module = replace_stub_bodies(&module, &replacements)?;
+                }
+
+                wit_component::embed_component_metadata(
+                    &mut module,
+                    &resolve,
+                    world_id,
+                    wit_component::StringEncoding::UTF8,
+                )?;
+                wit_component::ComponentEncoder::default()
+                    .module(&module)?
+                    .validate(true)
+                    .encode()
+            }
+
+            /// Rebuilds the dummy `module`, swapping the `unreachable` body of every function
+            /// exported under one of the names in `bodies` for the given instructions.
+            fn replace_stub_bodies(
+                module: &[u8],
+                bodies: &[(String, &[wasm_encoder::Instruction<'_>])],
+            ) -> anyhow::Result<Vec<u8>> {
+                use anyhow::Context;
+                use std::collections::HashMap;
+                use wasmparser::{ExternalKind, Parser, Payload, TypeRef};
+
+                // Code section entries are numbered after the imported functions
+                let mut imported_funcs = 0;
+                let mut exports = HashMap::new();
+                for payload in Parser::new(0).parse_all(module) {
+                    match payload? {
+                        Payload::ImportSection(reader) => {
+                            for import in reader {
+                                if let TypeRef::Func(_) = import?.ty {
+                                    imported_funcs += 1;
+                                }
+                            }
+                        }
+                        Payload::ExportSection(reader) => {
+                            for export in reader {
+                                let export = export?;
+                                if export.kind == ExternalKind::Func {
+                                    exports.insert(export.name, export.index);
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                let mut replacements = HashMap::new();
+                for (name, body) in bodies {
+                    let index = exports
+                        .get(name.as_str())
+                        .with_context(|| format!("The stub module does not export `{}`", name))?;
+                    replacements.insert(index - imported_funcs, *body);
+                }
+
+                // Every other section is copied as is
+                let mut encoded = wasm_encoder::Module::new();
+                let mut code = wasm_encoder::CodeSection::new();
+                let mut remaining = 0;
+                for payload in Parser::new(0).parse_all(module) {
+                    let payload = payload?;
+                    match &payload {
+                        Payload::CodeSectionStart { count, .. } => remaining = *count,
+                        Payload::CodeSectionEntry(body) => {
+                            match replacements.get(&code.len()) {
+                                Some(instructions) => {
+                                    let mut function = wasm_encoder::Function::new([]);
+                                    for instruction in instructions.iter() {
+                                        function.instruction(instruction);
+                                    }
+                                    function.instruction(&wasm_encoder::Instruction::End);
+                                    code.function(&function);
+                                }
+                                None => {
+                                    code.raw(body.as_bytes());
+                                }
+                            }
+                            remaining -= 1;
+                            if remaining == 0 {
+                                encoded.section(&code);
+                            }
+                        }
+                        _ => {
+                            if let Some((id, range)) = payload.as_section() {
+                                encoded.section(&wasm_encoder::RawSection {
+                                    id,
+                                    data: &module[range],
+                                });
+                            }
+                        }
+                    }
+                }
+                Ok(encoded.finish())
+            }
+        }
+    }
+
+    /// Generates `check_compatibility`, which compares a component against the world before
+    /// anything gets linked, and the `WorldMismatch` error it reports.
+    fn generate_compatibility_check(&self) -> Result<TokenStream> {
+        let world_name = &self.world.name;
+        let imports = self.generate_interface_signatures(&self.world.imports)?;
+        let exports = self.generate_interface_signatures(&self.world.exports)?;
+
+        Ok(quote! {
+            /// Signature of a function of the world, as declared in WIT.
+            #[derive(Debug, Clone)]
+            pub struct FunctionSignature {
+                pub name: &'static str,
+                /// The WIT declaration, e.g. `add: func(a: s32, b: s32) -> s32;`.
+                pub wit: &'static str,
+                /// Parameter names next to their types, in declaration order.
+                pub params: Vec<(&'static str, wasm_component_layer::ValueType)>,
+                pub results: Vec<wasm_component_layer::ValueType>,
+                pub ty: wasm_component_layer::FuncType,
+            }
+
+            /// An interface of the world with all of its functions.
+            #[derive(Debug, Clone)]
+            pub struct InterfaceSignature {
+                pub name: &'static str,
+                /// `None` for interfaces declared inline in the world, whose functions live on
+                /// the root of the component.
+                pub id: Option<wasm_component_layer::InterfaceIdentifier>,
+                pub functions: Vec<FunctionSignature>,
+            }
+
+            impl InterfaceSignature {
+                /// Whether `name` refers to this interface, either by its plain name (`math`)
+                /// or fully qualified (`example:calculator/math@0.1.0`).
+                pub fn matches(&self, name: &str) -> bool {
+                    self.name == name || self.id.as_ref().is_some_and(|id| id.to_string() == name)
+                }
+
+                /// The fully qualified name, or the plain one for inline interfaces.
+                pub fn qualified_name(&self) -> String {
+                    match &self.id {
+                        Some(id) => id.to_string(),
+                        None => self.name.to_string(),
+                    }
+                }
+
+                pub fn function(&self, name: &str) -> Option<&FunctionSignature> {
+                    self.functions.iter().find(|function| function.name == name)
+                }
+            }
+
+            /// The interfaces the world imports, as compiled into the bindings. Built on first
+            /// use.
+            pub fn world_imports() -> &'static [InterfaceSignature] {
+                static IMPORTS: std::sync::LazyLock<Vec<InterfaceSignature>> =
+                    std::sync::LazyLock::new(|| vec![#(#imports),*]);
+                &IMPORTS
+            }
+
+            /// The interfaces the world exports, as compiled into the bindings. Built on first
+            /// use.
+            pub fn world_exports() -> &'static [InterfaceSignature] {
+                static EXPORTS: std::sync::LazyLock<Vec<InterfaceSignature>> =
+                    std::sync::LazyLock::new(|| vec![#(#exports),*]);
+                &EXPORTS
+            }
+
+            /// A single difference between a component and the world. Signatures are
+            /// rendered as WIT function declarations.
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub enum Mismatch {
+                /// The world expects the component to export a function it doesn't have.
+                MissingExport {
+                    interface: String,
+                    function: String,
+                    expected: String,
+                },
+                /// The component imports a function the world doesn't provide.
+                UnexpectedImport {
+                    interface: String,
+                    function: String,
+                    actual: String,
+                },
+                /// Both sides know the function, but disagree on its type.
+                Signature {
+                    interface: String,
+                    function: String,
+                    expected: String,
+                    actual: String,
+                },
+            }
+
+            /// Returned by [`check_compatibility`] when a component doesn't match the world.
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub struct WorldMismatch {
+                pub mismatches: Vec<Mismatch>,
+            }
+
+            impl std::fmt::Display for WorldMismatch {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    writeln!(f, "component does not match world `{}`:", #world_name)?;
+                    for mismatch in &self.mismatches {
+                        match mismatch {
+                            Mismatch::MissingExport { interface, expected, .. } => {
+                                writeln!(f, "  missing export in `{}`:", interface)?;
+                                writeln!(f, "    {}", expected)?;
+                            }
+                            Mismatch::UnexpectedImport { interface, actual, .. } => {
+                                writeln!(f, "  unexpected import from `{}`:", interface)?;
+                                writeln!(f, "    {}", actual)?;
+                            }
+                            Mismatch::Signature { interface, expected, actual, .. } => {
+                                writeln!(f, "  signature mismatch in `{}`:", interface)?;
+                                writeln!(f, "    expected: {}", expected)?;
+                                writeln!(f, "       found: {}", actual)?;
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+            }
+
+            impl std::error::Error for WorldMismatch {}
+
+            /// Renders a runtime type the way it is written in WIT, as far as it is known.
+            fn wit_value_type(ty: &wasm_component_layer::ValueType) -> String {
+                use wasm_component_layer::ValueType;
+                match ty {
+                    ValueType::Bool => "bool".to_string(),
+                    ValueType::S8 => "s8".to_string(),
+                    ValueType::U8 => "u8".to_string(),
+                    ValueType::S16 => "s16".to_string(),
+                    ValueType::U16 => "u16".to_string(),
+                    ValueType::S32 => "s32".to_string(),
+                    ValueType::U32 => "u32".to_string(),
+                    ValueType::S64 => "s64".to_string(),
+                    ValueType::U64 => "u64".to_string(),
+                    ValueType::F32 => "f32".to_string(),
+                    ValueType::F64 => "f64".to_string(),
+                    ValueType::Char => "char".to_string(),
+                    ValueType::String => "string".to_string(),
+                    ValueType::List(list) => format!("list<{}>", wit_value_type(&list.element_ty())),
+                    ValueType::Option(option) => format!("option<{}>", wit_value_type(&option.some_ty())),
+                    ValueType::Record(_) => "record".to_string(),
+                    ValueType::Tuple(_) => "tuple".to_string(),
+                    ValueType::Variant(_) => "variant".to_string(),
+                    ValueType::Enum(_) => "enum".to_string(),
+                    ValueType::Result(_) => "result".to_string(),
+                    ValueType::Flags(_) => "flags".to_string(),
+                    ValueType::Own(_) => "own".to_string(),
+                    ValueType::Borrow(_) => "borrow".to_string(),
+                }
+            }
+
+            /// Renders a function found in a component, which carries no parameter names.
+            fn wit_func_type(name: &str, ty: &wasm_component_layer::FuncType) -> String {
+                let params = ty.params().iter().map(wit_value_type).collect::<Vec<_>>().join(", ");
+                match ty.results() {
+                    [] => format!("{}: func({});", name, params),
+                    [result] => format!("{}: func({}) -> {};", name, params, wit_value_type(result)),
+                    results => format!(
+                        "{}: func({}) -> ({});",
+                        name,
+                        params,
+                        results.iter().map(wit_value_type).collect::<Vec<_>>().join(", ")
+                    ),
+                }
+            }
+
+            /// Maps a version to its semver compatibility track, following the component model:
+            /// `1.2.3` is compatible with every `1.x.y`, `0.2.3` with every `0.2.x`, while
+            /// `0.0.x` versions and prereleases are only compatible with themselves.
+            fn compat_track(version: &semver::Version) -> semver::Version {
+                let mut version = version.clone();
+                version.build = semver::BuildMetadata::EMPTY;
+                if !version.pre.is_empty() {
+                    return version;
+                }
+                if version.major != 0 {
+                    version.minor = 0;
+                    version.patch = 0;
+                } else if version.minor != 0 {
+                    version.patch = 0;
+                }
+                version
+            }
+
+            /// Whether `actual` names the same interface as `expected`, at a semver compatible
+            /// version.
+            fn is_compatible(
+                expected: &wasm_component_layer::InterfaceIdentifier,
+                actual: &wasm_component_layer::InterfaceIdentifier,
+            ) -> bool {
+                expected.name() == actual.name()
+                    && expected.package().name() == actual.package().name()
+                    && match (expected.package().version(), actual.package().version()) {
+                        (Some(expected), Some(actual)) => compat_track(expected) == compat_track(actual),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+
+            /// Picks the newest instance that is semver compatible with `expected`.
+            fn newest_compatible<'a, I: 'a>(
+                instances: impl Iterator<Item = (&'a wasm_component_layer::InterfaceIdentifier, &'a I)>,
+                expected: &wasm_component_layer::InterfaceIdentifier,
+            ) -> Option<(&'a wasm_component_layer::InterfaceIdentifier, &'a I)> {
+                instances
+                    .filter(|(id, _)| is_compatible(expected, id))
+                    .max_by(|(a, _), (b, _)| a.package().version().cmp(&b.package().version()))
+            }
+
+            /// Every identifier under which `component` imports a version of `expected`. Falls
+            /// back to `expected` itself if the component doesn't import the interface at all.
+            fn compatible_imports(
+                component: &wasm_component_layer::Component,
+                expected: &wasm_component_layer::InterfaceIdentifier,
+            ) -> Vec<wasm_component_layer::InterfaceIdentifier> {
+                let ids: Vec<_> = component
+                    .imports()
+                    .instances()
+                    .map(|(id, _)| id)
+                    .filter(|id| is_compatible(expected, id))
+                    .cloned()
+                    .collect();
+                if ids.is_empty() {
+                    vec![expected.clone()]
+                } else {
+                    ids
+                }
+            }
+
+            /// Resolves the identifier under which `component` exports `expected`, preferring the
+            /// exact version over the newest compatible one.
+            fn compatible_export(
+                component: &wasm_component_layer::Component,
+                expected: &wasm_component_layer::InterfaceIdentifier,
+            ) -> wasm_component_layer::InterfaceIdentifier {
+                if component.exports().instance(expected).is_some() {
+                    return expected.clone();
+                }
+                newest_compatible(component.exports().instances(), expected)
+                    .map(|(id, _)| id.clone())
+                    .unwrap_or_else(|| expected.clone())
+            }
+
+            /// Compares the imports and exports of `component` against the world the bindings
+            /// were generated for, without linking or instantiating anything.
+            pub fn check_compatibility(
+                component: &wasm_component_layer::Component,
+            ) -> std::result::Result<(), WorldMismatch> {
+                let mut mismatches = Vec::new();
+
+                // Every function the component imports has to be provided by the world.
+                // Functions of inline interfaces are imported on the root.
+                let imports = world_imports();
+                for (name, actual) in component.imports().root().funcs() {
+                    let interface = imports
+                        .iter()
+                        .find(|interface| interface.id.is_none() && interface.function(name).is_some());
+                    match interface {
+                        None => mismatches.push(Mismatch::UnexpectedImport {
+                            interface: #world_name.to_string(),
+                            function: name.to_string(),
+                            actual: wit_func_type(name, &actual),
+                        }),
+                        Some(interface) => {
+                            let expected = interface.function(name).unwrap();
+                            if expected.ty != actual {
+                                mismatches.push(Mismatch::Signature {
+                                    interface: interface.name.to_string(),
+                                    function: name.to_string(),
+                                    expected: expected.wit.to_string(),
+                                    actual: wit_func_type(name, &actual),
+                                });
+                            }
+                        }
+                    }
+                }
+                for (id, instance) in component.imports().instances() {
+                    let interface = imports.iter().find(|interface| {
+                        interface.id.as_ref().is_some_and(|expected| is_compatible(expected, id))
+                    });
+                    for (name, actual) in instance.funcs() {
+                        let expected = interface.and_then(|interface| interface.function(name));
+                        match expected {
+                            None => mismatches.push(Mismatch::UnexpectedImport {
+                                interface: id.to_string(),
+                                function: name.to_string(),
+                                actual: wit_func_type(name, &actual),
+                            }),
+                            Some(expected) if expected.ty != actual => {
+                                mismatches.push(Mismatch::Signature {
+                                    interface: id.to_string(),
+                                    function: name.to_string(),
+                                    expected: expected.wit.to_string(),
+                                    actual: wit_func_type(name, &actual),
+                                })
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+
+                // Every function the world exports has to be implemented by the component
+                for interface in world_exports() {
+                    let instance = match &interface.id {
+                        Some(id) => component.exports().instance(id).or_else(|| {
+                            newest_compatible(component.exports().instances(), id)
+                                .map(|(_, instance)| instance)
+                        }),
+                        None => Some(component.exports().root()),
+                    };
+                    for expected in &interface.functions {
+                        match instance.and_then(|instance| instance.func(expected.name)) {
+                            None => mismatches.push(Mismatch::MissingExport {
+                                interface: interface.qualified_name(),
+                                function: expected.name.to_string(),
+                                expected: expected.wit.to_string(),
+                            }),
+                            Some(actual) if actual != expected.ty => {
+                                mismatches.push(Mismatch::Signature {
+                                    interface: interface.qualified_name(),
+                                    function: expected.name.to_string(),
+                                    expected: expected.wit.to_string(),
+                                    actual: wit_func_type(expected.name, &actual),
+                                })
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+
+                if mismatches.is_empty() {
+                    Ok(())
+                } else {
+                    Err(WorldMismatch { mismatches })
+                }
+            }
+        })
+    }
+
+    /// Generates an `InterfaceSignature` expression for every interface in `items`.
+    fn generate_interface_signatures<'b>(
+        &self,
+        items: impl IntoIterator<Item = (&'b WorldKey, &'b WorldItem)>,
+    ) -> Result<Vec<TokenStream>> {
+        let mut signatures = Vec::new();
+        for (key, item) in items {
+            let WorldItem::Interface { id, .. } = item else {
+                continue;
+            };
+            let interface = &self.resolve.interfaces[*id];
+            let name = self.get_interface_name(key);
+            let interface_id = match key {
+                WorldKey::Name(_) => quote! { None },
+                WorldKey::Interface(_) => {
+                    let interface_id = self.interface_identifier(interface)?;
+                    quote! { Some(#interface_id) }
+                }
+            };
+
+            let interface_path = self.interface_path(key)?;
+            let mut functions = Vec::new();
+            for (func_name, function) in &interface.functions {
+                let wit = self.wit_signature(function);
+                let param_names = function.params.iter().map(|(name, _)| name);
+                let (param_types, result_types) =
+                    self.function_value_types(&interface_path, function)?;
+                functions.push(quote! {
+                    FunctionSignature {
+                        name: #func_name,
+                        wit: #wit,
+                        params: vec![#((#param_names, #param_types)),*],
+                        results: vec![#(#result_types),*],
+                        ty: wasm_component_layer::FuncType::new(
+                            [#(#param_types),*],
+                            [#(#result_types),*],
+                        ),
+                    }
+                });
+            }
+
+            signatures.push(quote! {
+                InterfaceSignature {
+                    name: #name,
+                    id: #interface_id,
+                    functions: vec![#(#functions),*],
+                }
+            });
+        }
+        Ok(signatures)
+    }
+
+    fn generate_import_interface(
+        &self,
+        key: &WorldKey,
+        interface: &Interface,
+    ) -> Result<(TokenStream, TokenStream, TokenStream)> {
+        let interface_name = self.get_interface_name(key);
+        let trait_name = format_ident!("{}", interface_name);
+        let field_name = format_ident!("{}", interface_name.to_lowercase());
+
+        let mut trait_methods = Vec::new();
+        for (_, function) in &interface.functions {
+            let method = self
+                .generate_trait_method(function)
+                .with_context(|| format!("In function '{}'", function.name))?;
+            trait_methods.push(method);
+            trait_methods.push(self.generate_try_trait_method(function)?);
+        }
+
+        let trait_def = quote! {
+            pub trait #trait_name {
+                #(#trait_methods)*
+            }
+        };
+
+        // The import itself is linked in the instantiate function
+        let closures = self.generate_closures_impl(&interface_name, key, interface)?;
+        let forwarder = self.generate_forwarder_impl(&interface_name, interface)?;
+        let recording_stub = if self.options.stubs {
+            self.generate_recording_stub(&interface_name, interface)?
+        } else {
+            quote! {}
+        };
+        let impl_def = quote! {
+            #closures
+            #forwarder
+            #recording_stub
+        };
+
+        let send = self.send_bound();
+        let field_def = if self.options.optional_imports {
+            quote! {
+                pub #field_name: Option<Box<dyn #trait_name #send>>
+            }
+        } else {
+            quote! {
+                pub #field_name: Box<dyn #trait_name #send>
+            }
+        };
+
+        Ok((trait_def, impl_def, field_def))
+    }
+
+    /// Generates `<Interface>Closures`, the implementation of the import trait that
+    /// `ImportsBuilder` fills with closures.
+    fn generate_closures_impl(
+        &self,
+        interface_name: &str,
+        key: &WorldKey,
+        interface: &Interface,
+    ) -> Result<TokenStream> {
+        let trait_name = format_ident!("{}", interface_name);
+        let closures_name = format_ident!("{}Closures", to_upper_camel_case(interface_name));
+        let interface_path = self.interface_path(key)?;
+
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        for (_, function) in &interface.functions {
+            let func_ident = format_ident!("{}", function.name);
+            let params = self.generate_function_params(function)?;
+            let param_names = function
+                .params
+                .iter()
+                .map(|(name, _)| format_ident!("{}", name));
+            let param_types = function
+                .params
+                .iter()
+                .map(|(_, ty)| self.type_to_rust_type(ty))
+                .collect::<Result<Vec<_>>>()?;
+            let result_type = match &function.result {
+                Some(ty) => self.type_to_rust_type(ty)?,
+                None => quote! { () },
+            };
+            let return_type = self.generate_method_return_type(function)?;
+            let try_ident = format_ident!("try_{}", function.name);
+            let try_return_type = self.generate_try_return_type(function)?;
+            let message = format!("unimplemented import `{}#{}`", interface_path, function.name);
+
+            let send = self.send_bound();
+            fields.push(quote! {
+                #func_ident: Option<Box<dyn FnMut(#(#param_types),*) -> #result_type #send>>
+            });
+            // An unset closure traps the guest through the error, without unwinding
+            let try_body = self.ready_result(quote! {
+                match self.#func_ident.as_mut() {
+                    Some(f) => Ok(f(#(#param_names),*)),
+                    None => Err(anyhow::anyhow!(#message)),
+                }
+            });
+            let body = self.unwrap_try_call(function);
+            methods.push(quote! {
+                fn #func_ident(&mut self, #(#params),*) #return_type {
+                    #body
+                }
+
+                fn #try_ident(&mut self, #(#params),*) #try_return_type {
+                    #try_body
+                }
+            });
+        }
+
+        let doc = format!(
+            "Implements `{}` with closures, see [`Imports::builder`].",
+            interface_name
+        );
+        Ok(quote! {
+            #[doc = #doc]
+            #[derive(Default)]
+            pub struct #closures_name {
+                #(#fields,)*
+            }
+
+            impl #trait_name for #closures_name {
+                #(#methods)*
+            }
+        })
+    }
+
+    /// Generates `<Interface>Forwarder`, which implements the import trait by calling the
+    /// matching export of another instance, as returned by `Exports::forwarder`.
+    fn generate_forwarder_impl(
+        &self,
+        interface_name: &str,
+        interface: &Interface,
+    ) -> Result<TokenStream> {
+        let trait_name = format_ident!("{}", interface_name);
+        let forwarder_name = format_ident!("{}Forwarder", to_upper_camel_case(interface_name));
+        // Exports are looked up by their plain name, which works for inline interfaces too
+        let target_interface = interface_name;
+
+        let mut methods = Vec::new();
+        for (_, function) in &interface.functions {
+            let func_ident = format_ident!("{}", function.name);
+            let try_ident = format_ident!("try_{}", function.name);
+            let func_name = &function.name;
+            let params = self.generate_function_params(function)?;
+            let param_names = function
+                .params
+                .iter()
+                .map(|(name, _)| format_ident!("{}", name));
+            let return_type = self.generate_method_return_type(function)?;
+            let try_return_type = self.generate_try_return_type(function)?;
+            let result_type = match &function.result {
+                Some(ty) => self.type_to_rust_type(ty)?,
+                None => quote! { () },
+            };
+            let result = if function.result.is_some() {
+                quote! {
+                    let result = results
+                        .first()
+                        .context("forwarded call returned no result")?;
+                    wasm_component_layer::ComponentType::from_value(result)
+                }
+            } else {
+                quote! {
+                    let _ = results;
+                    Ok(())
+                }
+            };
+
+            // A failed call is returned as an error, which traps the guest
+            let try_body = if self.options.async_mode {
+                quote! {
+                    let call = (|| -> anyhow::Result<BoxFuture<'static, anyhow::Result<Vec<wasm_component_layer::Value>>>> {
+                        let args: Vec<wasm_component_layer::Value> = vec![#(
+                            wasm_component_layer::ComponentType::into_value(#param_names)?
+                        ),*];
+                        Ok((self.call)(#target_interface, #func_name, &args))
+                    })();
+                    Box::pin(async move {
+                        use anyhow::Context;
+
+                        let results = call?.await.with_context(|| {
+                            format!("forwarding `{}#{}` failed", #target_interface, #func_name)
+                        })?;
+                        #result
+                    })
+                }
+            } else {
+                quote! {
+                    (|| -> anyhow::Result<#result_type> {
+                        use anyhow::Context;
+
+                        let args: Vec<wasm_component_layer::Value> = vec![#(
+                            wasm_component_layer::ComponentType::into_value(#param_names)?
+                        ),*];
+                        let results = (self.call)(#target_interface, #func_name, &args)
+                            .with_context(|| {
+                                format!("forwarding `{}#{}` failed", #target_interface, #func_name)
+                            })?;
+                        #result
+                    })()
+                }
+            };
+            let body = self.unwrap_try_call(function);
+            methods.push(quote! {
+                fn #func_ident(&mut self, #(#params),*) #return_type {
+                    #body
+                }
+
+                fn #try_ident(&mut self, #(#params),*) #try_return_type {
+                    #try_body
+                }
+            });
+        }
+
+        let doc = format!(
+            "Implements `{}` by calling the `{}` exports of another instance.",
+            interface_name, target_interface
+        );
+        let call_result = if self.options.async_mode {
+            quote! { BoxFuture<'static, anyhow::Result<Vec<wasm_component_layer::Value>>> }
+        } else {
+            quote! { anyhow::Result<Vec<wasm_component_layer::Value>> }
+        };
+        Ok(quote! {
+            #[doc = #doc]
+            pub struct #forwarder_name<F> {
+                call: F,
+            }
+
+            impl<F> #forwarder_name<F> {
+                pub fn new(call: F) -> Self {
+                    Self { call }
+                }
+            }
+
+            impl<F> #trait_name for #forwarder_name<F>
+            where
+                F: FnMut(&str, &str, &[wasm_component_layer::Value]) -> #call_result,
+            {
+                #(#methods)*
+            }
+        })
+    }
+
+    /// Generates `Imports::builder()`, which satisfies the imports with one closure per
+    /// function, e.g. `.console_print(|line| println!("{line}"))`.
+    fn generate_imports_builder(&self) -> Result<TokenStream> {
+        let mut fields = Vec::new();
+        let mut setters = Vec::new();
+        let mut field_inits = Vec::new();
+        let mut missing_checks = Vec::new();
+        let mut field_names = Vec::new();
+
+        for (key, item) in &self.world.imports {
+            let WorldItem::Interface { id, .. } = item else {
+                continue;
+            };
+            let interface = &self.resolve.interfaces[*id];
+            let interface_name = self.get_interface_name(key);
+            let field_name = format_ident!("{}", interface_name.to_lowercase());
+            let closures_name = format_ident!("{}Closures", to_upper_camel_case(&interface_name));
+            let mut func_idents = Vec::new();
+
+            for (_, function) in &interface.functions {
+                let func_ident = format_ident!("{}", function.name);
+                func_idents.push(func_ident.clone());
+                let setter = format_ident!("{}_{}", field_name, function.name);
+                let param_types = function
+                    .params
+                    .iter()
+                    .map(|(_, ty)| self.type_to_rust_type(ty))
+                    .collect::<Result<Vec<_>>>()?;
+                let result_type = match &function.result {
+                    Some(ty) => self.type_to_rust_type(ty)?,
+                    None => quote! { () },
+                };
+                let doc = format!("Implements `{}.{}` with `f`.", interface_name, function.name);
+                let send = self.send_bound();
+
+                setters.push(quote! {
+                    #[doc = #doc]
+                    pub fn #setter(
+                        mut self,
+                        f: impl FnMut(#(#param_types),*) -> #result_type #send + 'static,
+                    ) -> Self {
+                        self.#field_name.#func_ident = Some(Box::new(f));
+                        self
+                    }
+                });
+            }
+
+            let interface_path = self.interface_path(key)?;
+            missing_checks.push(quote! {
+                if self.#field_name.is_none() {
+                    missing.push(#interface_path);
+                }
+            });
+
+            field_names.push(field_name.clone());
+            fields.push(quote! { #field_name: #closures_name });
+            if self.options.optional_imports {
+                // An interface without any closure is left out, so it shows up in
+                // `missing_imports`
+                let unset = if func_idents.is_empty() {
+                    quote! { true }
+                } else {
+                    quote! { #(self.#field_name.#func_idents.is_none())&&* }
+                };
+                field_inits.push(quote! {
+                    #field_name: if #unset {
+                        None
+                    } else {
+                        Some(Box::new(self.#field_name))
+                    }
+                });
+            } else {
+                field_inits.push(quote! { #field_name: Box::new(self.#field_name) });
+            }
+        }
+
+        let optional_imports = if self.options.optional_imports {
+            quote! {
+                impl Default for Imports {
+                    /// No interfaces at all, every import traps when called.
+                    fn default() -> Self {
+                        Self {
+                            #(#field_names: None,)*
+                        }
+                    }
+                }
+
+                impl Imports {
+                    /// The interfaces left out, as `namespace:package/interface`.
+                    pub fn missing_imports(&self) -> Vec<&'static str> {
+                        let mut missing = Vec::new();
+                        #(#missing_checks)*
+                        missing
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        Ok(quote! {
+            #optional_imports
+
+            /// Builds [`Imports`] from closures. Functions left unset trap the guest when
+            /// called.
+            #[derive(Default)]
+            pub struct ImportsBuilder {
+                #(#fields,)*
+            }
+
+            impl ImportsBuilder {
+                #(#setters)*
+
+                pub fn build(self) -> Imports {
+                    Imports {
+                        #(#field_inits,)*
+                    }
+                }
+            }
+
+            impl Imports {
+                pub fn builder() -> ImportsBuilder {
+                    ImportsBuilder::default()
+                }
+            }
+        })
+    }
+
+    /// Generates `Recording<Interface>`, an implementation of the import trait that logs
+    /// every call as a `<Interface>Call` and answers with a configured closure or
+    /// `Default::default()`.
+    fn generate_recording_stub(
+        &self,
+        interface_name: &str,
+        interface: &Interface,
+    ) -> Result<TokenStream> {
+        let trait_name = format_ident!("{}", interface_name);
+        let camel_name = to_upper_camel_case(interface_name);
+        let recording_name = format_ident!("Recording{}", camel_name);
+        let state_name = format_ident!("Recording{}State", camel_name);
+        let call_name = format_ident!("{}Call", camel_name);
+        let state_type = self.shared_type(quote! { #state_name });
+        let lock_state = self.shared_lock(quote! { self.state });
+
+        let mut variants = Vec::new();
+        let mut handler_fields = Vec::new();
+        let mut setters = Vec::new();
+        let mut methods = Vec::new();
+
+        for (_, function) in &interface.functions {
+            let func_ident = format_ident!("{}", function.name);
+            let variant = format_ident!("{}", to_upper_camel_case(&function.name));
+            let params = self.generate_function_params(function)?;
+            let param_names: Vec<_> = function
+                .params
+                .iter()
+                .map(|(name, _)| format_ident!("{}", name))
+                .collect();
+            let param_types = function
+                .params
+                .iter()
+                .map(|(_, ty)| self.type_to_rust_type(ty))
+                .collect::<Result<Vec<_>>>()?;
+            let result_type = match &function.result {
+                Some(ty) => self.type_to_rust_type(ty)?,
+                None => quote! { () },
+            };
+            let return_type = self.generate_method_return_type(function)?;
+
+            variants.push(quote! {
+                #variant { #(#params),* }
+            });
+            let send = self.send_bound();
+            let handler_type = quote! { Box<dyn FnMut(#(#param_types),*) -> #result_type #send> };
+            // Shared, so a call can take the handler out and release the state before calling
+            // it. The handler may then use the stub itself, e.g. to look at the calls.
+            let shared_handler = self.shared_type(handler_type.clone());
+            handler_fields.push(quote! {
+                #func_ident: Option<#shared_handler>
+            });
+            let new_handler = self.shared_new(quote! { handler });
+
+            let with_doc = format!("Answers calls to `{}` with `handler`.", function.name);
+            let with_name = format_ident!("with_{}", function.name);
+            setters.push(quote! {
+                #[doc = #with_doc]
+                pub fn #with_name(
+                    self,
+                    handler: impl FnMut(#(#param_types),*) -> #result_type #send + 'static,
+                ) -> Self {
+                    let handler: #handler_type = Box::new(handler);
+                    #lock_state.#func_ident = Some(#new_handler);
+                    self
+                }
+            });
+            if function.result.is_some() {
+                let returning_doc =
+                    format!("Answers every call to `{}` with `value`.", function.name);
+                let returning_name = format_ident!("returning_{}", function.name);
+                let ignored = param_names.iter().map(|_| quote! { _ });
+                setters.push(quote! {
+                    #[doc = #returning_doc]
+                    pub fn #returning_name(self, value: #result_type) -> Self {
+                        self.#with_name(move |#(#ignored),*| value.clone())
+                    }
+                });
+            }
+
+            let lock_handler = self.shared_lock(quote! { handler });
+            let body = self.ready_result(quote! {
+                let handler = {
+                    let mut state = #lock_state;
+                    state.calls.push(#call_name::#variant {
+                        #(#param_names: #param_names.clone()),*
+                    });
+                    state.#func_ident.clone()
+                };
+                match handler {
+                    Some(handler) => (*#lock_handler)(#(#param_names),*),
+                    None => Default::default(),
+                }
+            });
+            methods.push(quote! {
+                fn #func_ident(&mut self, #(#params),*) #return_type {
+                    #body
+                }
+            });
+        }
+
+        let recording_doc = format!(
+            "Records every call to `{}` and answers with canned values.",
+            interface_name
+        );
+        let call_doc = format!("A call recorded by [`{}`].", recording_name);
+
+        Ok(quote! {
+            #[doc = #call_doc]
+            #[derive(Debug, Clone, PartialEq)]
+            pub enum #call_name {
+                #(#variants,)*
+            }
+
+            #[derive(Default)]
+            struct #state_name {
+                calls: Vec<#call_name>,
+                #(#handler_fields,)*
+            }
+
+            #[doc = #recording_doc]
+            ///
+            /// Clones share their state, so keep a clone around to inspect the calls after
+            /// handing one to `Imports`.
+            #[derive(Clone, Default)]
+            pub struct #recording_name {
+                state: #state_type,
+            }
+
+            impl #recording_name {
+                #(#setters)*
+
+                /// The calls made so far, oldest first.
+                pub fn calls(&self) -> Vec<#call_name> {
+                    #lock_state.calls.clone()
+                }
+
+                /// Forgets the recorded calls, but keeps the configured answers.
+                pub fn clear(&self) {
+                    #lock_state.calls.clear();
+                }
+            }
+
+            impl #trait_name for #recording_name {
+                #(#methods)*
+            }
+        })
+    }
+
+    fn generate_export_interface(
+        &self,
+        key: &WorldKey,
+        interface: &Interface,
+    ) -> Result<(TokenStream, TokenStream, TokenStream)> {
+        let interface_name = self.get_interface_name(key);
+        let trait_name = format_ident!("{}", interface_name);
+        let impl_name = format_ident!("{}Impl", interface_name);
+        let field_name = format_ident!("{}", interface_name.to_lowercase());
+
+        let mut trait_methods = Vec::new();
+        let mut impl_methods = Vec::new();
+        let mut struct_fields = Vec::new();
+
+        for (func_name, function) in &interface.functions {
+            let trait_method = self
+                .generate_trait_method(function)
+                .with_context(|| format!("In function '{}'", function.name))?;
+            let impl_method = self.generate_impl_method(key, func_name, function)?;
+            let func_field = format_ident!("{}", func_name);
+
+            trait_methods.push(trait_method);
+            impl_methods.push(impl_method);
+
+            let param_types = self.get_function_param_types(function)?;
+            let return_type = self.get_function_return_type(function)?;
+
+            struct_fields.push(quote! {
+                #func_field: wasm_component_layer::TypedFunc<#param_types, #return_type>
+            });
+        }
+
+        let trait_def = quote! {
+            pub trait #trait_name {
+                #(#trait_methods)*
+            }
+        };
+
+        let async_bounds = self.async_bounds();
+        let store_type = self.shared_type(quote! { wasm_component_layer::Store<T, E> });
+        let impl_def = quote! {
+            struct #impl_name<T, E>
+            where
+                E: wasm_runtime_layer::backend::WasmEngine,
+            {
+                store: #store_type,
+                #(#struct_fields,)*
+            }
+
+            impl<T, E> #trait_name for #impl_name<T, E>
+            where
+                E: wasm_runtime_layer::backend::WasmEngine,
+                #async_bounds
+            {
+                #(#impl_methods)*
+            }
+        };
+
+        let field_def = quote! {
+            pub #field_name: Box<dyn #trait_name>
+        };
+
+        Ok((trait_def, impl_def, field_def))
+    }
+
+    /// Generates `try_<function>`, which the bindings call instead of `<function>`. An error
+    /// traps the guest, so implementations can fail without panicking.
+    fn generate_try_trait_method(&self, function: &Function) -> Result<TokenStream> {
+        let func_ident = format_ident!("{}", function.name);
+        let try_ident = format_ident!("try_{}", function.name);
+        let params = self.generate_function_params(function)?;
+        let param_names = function
+            .params
+            .iter()
+            .map(|(name, _)| format_ident!("{}", name));
+        let return_type = self.generate_try_return_type(function)?;
+        let doc = format!(
+            "Calls `{}`, return an error instead to trap the guest.",
+            function.name
+        );
+
+        let body = if self.options.async_mode {
+            quote! {
+                let result = self.#func_ident(#(#param_names),*);
+                Box::pin(async move { Ok(result.await) })
+            }
+        } else {
+            quote! { Ok(self.#func_ident(#(#param_names),*)) }
+        };
+        Ok(quote! {
+            #[doc = #doc]
+            fn #try_ident(&mut self, #(#params),*) #return_type {
+                #body
+            }
+        })
+    }
+
+    /// The return type of `try_<function>`, an `anyhow::Result` wrapped in a `BoxFuture` in
+    /// async mode.
+    fn generate_try_return_type(&self, function: &Function) -> Result<TokenStream> {
+        let rust_type = match &function.result {
+            Some(ty) => self
+                .type_to_rust_type(ty)
+                .context("In the result type")?,
+            None => quote! { () },
+        };
+        if self.options.async_mode {
+            Ok(quote! { -> BoxFuture<'_, anyhow::Result<#rust_type>> })
+        } else {
+            Ok(quote! { -> anyhow::Result<#rust_type> })
+        }
+    }
+
+    /// Calls `try_<function>` and panics on an error, for import trait methods that are
+    /// implemented on top of their fallible counterpart.
+    fn unwrap_try_call(&self, function: &Function) -> TokenStream {
+        let try_ident = format_ident!("try_{}", function.name);
+        let param_names = function
+            .params
+            .iter()
+            .map(|(name, _)| format_ident!("{}", name));
+        if self.options.async_mode {
+            quote! {
+                let result = self.#try_ident(#(#param_names),*);
+                Box::pin(async move { result.await.unwrap_or_else(|error| panic!("{:#}", error)) })
+            }
+        } else {
+            quote! {
+                self.#try_ident(#(#param_names),*).unwrap_or_else(|error| panic!("{:#}", error))
+            }
+        }
+    }
+
+    fn generate_trait_method(&self, function: &Function) -> Result<TokenStream> {
+        let func_name = format_ident!("{}", function.name);
+        let params = self.generate_function_params(function)?;
+        let return_type = self.generate_method_return_type(function)?;
+
+        Ok(quote! {
+            fn #func_name(&mut self, #(#params),*) #return_type;
+        })
+    }
+
+    fn generate_impl_method(
+        &self,
+        key: &WorldKey,
+        func_name: &str,
+        function: &Function,
+    ) -> Result<TokenStream> {
+        let method_name = format_ident!("{}", function.name);
+        let func_field = format_ident!("{}", func_name);
+        let params = self.generate_function_params(function)?;
+        let return_type = self.generate_method_return_type(function)?;
+        let param_names: Vec<_> = function
+            .params
+            .iter()
+            .map(|(name, _)| format_ident!("{}", name))
+            .collect();
+
+        let param_tuple = if param_names.len() == 1 {
+            quote! { #(#param_names)* }
+        } else {
+            quote! { (#(#param_names),*) }
+        };
+
+        let lock_store = if self.options.single_threaded {
+            quote! { store.borrow_mut() }
+        } else {
+            quote! { store.lock().unwrap_or_else(std::sync::PoisonError::into_inner) }
+        };
+
+        // Calls the guest through `store` and `func`
+        let call = if self.options.tracing {
+            let interface_path = self.interface_path(key)?;
+            let traced = self.traced_call(
+                "export",
+                &interface_path,
+                func_name,
+                quote! { ?(#(&#param_names,)*) },
+                quote! {{
+                    let mut store_guard = #lock_store;
+                    func.call(&mut *store_guard, #param_tuple)
+                }},
+                quote! { Ok(result) => span.record("result", tracing::field::debug(result)) },
+            );
+            quote! {
+                let result = { #traced };
+                result.unwrap()
+            }
+        } else {
+            quote! {
+                let mut store_guard = #lock_store;
+                func.call(&mut *store_guard, #param_tuple).unwrap()
+            }
+        };
+
+        if self.options.async_mode {
+            // The guest can't yield, so it runs on the blocking pool instead of stalling
+            // the executor
+            return Ok(quote! {
+                fn #method_name(&mut self, #(#params),*) #return_type {
+                    let store = self.store.clone();
+                    let func = self.#func_field.clone();
+                    Box::pin(async move {
+                        tokio::task::spawn_blocking(move || { #call })
+                            .await
+                            .unwrap()
+                    })
+                }
+            });
+        }
+
+        Ok(quote! {
+            fn #method_name(&mut self, #(#params),*) #return_type {
+                let store = &self.store;
+                let func = &self.#func_field;
+                #call
+            }
+        })
+    }
+
+    /// Runs `call`, which evaluates to a `Result`, in a span named `kind`. `ok_arm` records
+    /// the result on `span`, errors and the duration are recorded here. `Instant` panics on
+    /// `wasm32-unknown-unknown`, so the duration is left out there.
+    fn traced_call(
+        &self,
+        kind: &str,
+        interface_path: &str,
+        func_name: &str,
+        args: TokenStream,
+        call: TokenStream,
+        ok_arm: TokenStream,
+    ) -> TokenStream {
+        quote! {
+            let span = tracing::info_span!(
+                #kind,
+                interface = #interface_path,
+                function = #func_name,
+                args = #args,
+                result = tracing::field::Empty,
+                error = tracing::field::Empty,
+                elapsed = tracing::field::Empty,
+            );
+            let _entered = span.enter();
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            let start = std::time::Instant::now();
+
+            let outcome = #call;
+            match &outcome {
+                #ok_arm,
+                Err(error) => span.record("error", tracing::field::display(error)),
+            };
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            span.record("elapsed", tracing::field::debug(start.elapsed()));
+            outcome
+        }
+    }
+
+    fn generate_function_params(&self, function: &Function) -> Result<Vec<TokenStream>> {
+        let mut params = Vec::new();
+        for (name, ty) in &function.params {
+            let param_name = format_ident!("{}", name);
+            let param_type = self
+                .type_to_rust_type(ty)
+                .with_context(|| format!("In parameter '{}'", name))?;
+            params.push(quote! { #param_name: #param_type });
+        }
+        Ok(params)
+    }
+
+    fn generate_return_type(&self, function: &Function) -> Result<TokenStream> {
+        match &function.result {
+            Some(ty) => {
+                let rust_type = self
+                    .type_to_rust_type(ty)
+                    .context("In the result type")?;
+                Ok(quote! { -> #rust_type })
+            }
+            None => Ok(TokenStream::new()),
+        }
+    }
+
+    /// The return type of a trait method, which is a `BoxFuture` in async mode.
+    fn generate_method_return_type(&self, function: &Function) -> Result<TokenStream> {
+        if !self.options.async_mode {
+            return self.generate_return_type(function);
+        }
+        let rust_type = match &function.result {
+            Some(ty) => self
+                .type_to_rust_type(ty)
+                .context("In the result type")?,
+            None => quote! { () },
+        };
+        Ok(quote! { -> BoxFuture<'_, #rust_type> })
+    }
+
+    /// The type of state shared between the bindings and the runtime.
+    fn shared_type(&self, inner: TokenStream) -> TokenStream {
+        if self.options.single_threaded {
+            quote! { std::rc::Rc<std::cell::RefCell<#inner>> }
+        } else {
+            quote! { std::sync::Arc<std::sync::Mutex<#inner>> }
+        }
+    }
+
+    fn shared_new(&self, value: TokenStream) -> TokenStream {
+        if self.options.single_threaded {
+            quote! { std::rc::Rc::new(std::cell::RefCell::new(#value)) }
+        } else {
+            quote! { std::sync::Arc::new(std::sync::Mutex::new(#value)) }
+        }
+    }
+
+    /// Borrows shared state mutably. A host function that trapped leaves its mutex
+    /// poisoned, which is ignored, the trap is reported to the caller instead.
+    fn shared_lock(&self, shared: TokenStream) -> TokenStream {
+        if self.options.single_threaded {
+            quote! { #shared.borrow_mut() }
+        } else {
+            quote! { #shared.lock().unwrap_or_else(std::sync::PoisonError::into_inner) }
+        }
+    }
+
+    /// `+ Send`, unless the bindings are single threaded.
+    fn send_bound(&self) -> TokenStream {
+        if self.options.single_threaded {
+            quote! {}
+        } else {
+            quote! { + Send }
+        }
+    }
+
+    /// Makes shared state capturable by host functions, which the runtime requires to be
+    /// `Send + Sync`. Access it through [`Self::captured`].
+    fn capture(&self, value: TokenStream) -> TokenStream {
+        if self.options.single_threaded {
+            quote! { SingleThreaded::new(#value) }
+        } else {
+            value
+        }
+    }
+
+    fn captured(&self, capture: TokenStream) -> TokenStream {
+        if self.options.single_threaded {
+            quote! { #capture.get() }
+        } else {
+            capture
+        }
+    }
+
+    /// Extra bounds on the store in async mode, which moves it to the blocking pool.
+    fn async_bounds(&self) -> TokenStream {
+        if self.options.async_mode {
+            quote! {
+                T: Send + 'static,
+                E: 'static,
+                wasm_component_layer::Store<T, E>: Send,
+            }
+        } else {
+            quote! {}
+        }
+    }
+
+    /// Wraps the body of a host-side method that computes its result synchronously.
+    fn ready_result(&self, body: TokenStream) -> TokenStream {
+        if self.options.async_mode {
+            quote! {
+                let result = { #body };
+                Box::pin(std::future::ready(result))
+            }
+        } else {
+            body
+        }
+    }
+
+    fn get_function_param_types(&self, function: &Function) -> Result<TokenStream> {
+        let param_types: Result<Vec<_>> = function
+            .params
+            .iter()
+            .map(|(_, ty)| self.type_to_rust_type(ty))
+            .collect();
+        let param_types = param_types?;
+
+        if param_types.len() == 1 {
+            Ok(quote! { #(#param_types)* })
+        } else {
+            Ok(quote! { (#(#param_types),*) })
+        }
+    }
+
+    fn get_function_return_type(&self, function: &Function) -> Result<TokenStream> {
+        match &function.result {
+            Some(ty) => self.type_to_rust_type(ty),
+            None => Ok(quote! { () }),
+        }
+    }
+
+    fn type_to_rust_type(&self, ty: &Type) -> Result<TokenStream> {
+        match ty {
+            Type::Bool => Ok(quote! { bool }),
+            Type::U8 => Ok(quote! { u8 }),
+            Type::U16 => Ok(quote! { u16 }),
+            Type::U32 => Ok(quote! { u32 }),
+            Type::U64 => Ok(quote! { u64 }),
+            Type::S8 => Ok(quote! { i8 }),
+            Type::S16 => Ok(quote! { i16 }),
+            Type::S32 => Ok(quote! { i32 }),
+            Type::S64 => Ok(quote! { i64 }),
+            Type::F32 => Ok(quote! { f32 }),
+            Type::F64 => Ok(quote! { f64 }),
+            Type::Char => Ok(quote! { char }),
+            Type::String => Ok(quote! { String }),
+            Type::ErrorContext => Err(self.unsupported_async_type(ty)),
+            Type::Id(type_id) => {
+                let type_def = &self.resolve.types[*type_id];
+                match &type_def.kind {
+                    TypeDefKind::Stream(_) | TypeDefKind::Future(_) => {
+                        Err(self.unsupported_async_type(ty))
+                    }
+                    TypeDefKind::List(element_type) => {
+                        let element_rust_type = self.type_to_rust_type(element_type)?;
+                        Ok(quote! { Vec<#element_rust_type> })
+                    }
+                    TypeDefKind::Option(some_type) => {
+                        let some_rust_type = self.type_to_rust_type(some_type)?;
+                        Ok(quote! { Option<#some_rust_type> })
+                    }
+                    TypeDefKind::Type(aliased) => self.type_to_rust_type(aliased),
+                    _ => {
+                        // For now, fallback to a generic type name
+                        let type_name = type_def.name.as_ref().with_context(|| {
+                            format!(
+                                "Unsupported type: anonymous '{}' types are not supported yet",
+                                type_def.kind.as_str()
+                            )
+                        })?;
+                        let rust_name = format_ident!("{}", type_name);
+                        Ok(quote! { #rust_name })
+                    }
+                }
+            }
+        }
+    }
+
+    /// The `ValueType`s of the parameters and the result of `function`, errors name the
+    /// function and the parameter they come from.
+    fn function_value_types(
+        &self,
+        interface_path: &str,
+        function: &Function,
+    ) -> Result<(Vec<TokenStream>, Vec<TokenStream>)> {
+        let params = function
+            .params
+            .iter()
+            .map(|(name, ty)| {
+                self.type_to_value_type(ty).with_context(|| {
+                    format!(
+                        "In function '{}#{}', parameter '{}'",
+                        interface_path, function.name, name
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let results = function
+            .result
+            .iter()
+            .map(|ty| {
+                self.type_to_value_type(ty).with_context(|| {
+                    format!("In function '{}#{}', result", interface_path, function.name)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((params, results))
+    }
+
+    fn type_to_value_type(&self, ty: &Type) -> Result<TokenStream> {
+        match ty {
+            Type::Bool => Ok(quote! { wasm_component_layer::ValueType::Bool }),
+            Type::U8 => Ok(quote! { wasm_component_layer::ValueType::U8 }),
+            Type::U16 => Ok(quote! { wasm_component_layer::ValueType::U16 }),
+            Type::U32 => Ok(quote! { wasm_component_layer::ValueType::U32 }),
+            Type::U64 => Ok(quote! { wasm_component_layer::ValueType::U64 }),
+            Type::S8 => Ok(quote! { wasm_component_layer::ValueType::S8 }),
+            Type::S16 => Ok(quote! { wasm_component_layer::ValueType::S16 }),
+            Type::S32 => Ok(quote! { wasm_component_layer::ValueType::S32 }),
+            Type::S64 => Ok(quote! { wasm_component_layer::ValueType::S64 }),
+            Type::F32 => Ok(quote! { wasm_component_layer::ValueType::F32 }),
+            Type::F64 => Ok(quote! { wasm_component_layer::ValueType::F64 }),
+            Type::Char => Ok(quote! { wasm_component_layer::ValueType::Char }),
+            Type::String => Ok(quote! { wasm_component_layer::ValueType::String }),
+            Type::Id(type_id) => {
+                let type_def = &self.resolve.types[*type_id];
+                match &type_def.kind {
+                    TypeDefKind::List(element_type) => {
+                        let element_value_type = self.type_to_value_type(element_type)?;
+                        Ok(quote! {
+                            wasm_component_layer::ValueType::List(
+                                wasm_component_layer::ListType::new(#element_value_type),
+                            )
+                        })
+                    }
+                    TypeDefKind::Option(some_type) => {
+                        let some_value_type = self.type_to_value_type(some_type)?;
+                        Ok(quote! {
+                            wasm_component_layer::ValueType::Option(
+                                wasm_component_layer::OptionType::new(#some_value_type),
+                            )
+                        })
+                    }
+                    TypeDefKind::Type(aliased) => self.type_to_value_type(aliased),
+                    TypeDefKind::Stream(_) | TypeDefKind::Future(_) => {
+                        Err(self.unsupported_async_type(ty))
+                    }
+                    kind => anyhow::bail!(
+                        "Unsupported type: '{}' types can't be passed across the component boundary yet",
+                        kind.as_str()
+                    ),
+                }
+            }
+            Type::ErrorContext => Err(self.unsupported_async_type(ty)),
+        }
+    }
+
+    /// The error for `stream`, `future` and `error-context`, which belong to the component
+    /// model's async proposal.
+    fn unsupported_async_type(&self, ty: &Type) -> anyhow::Error {
+        anyhow::anyhow!(
+            "Unsupported type: '{}' is a component model async type, which wasm_component_layer \
+             can't pass across the component boundary",
+            self.wit_type_name(ty)
+        )
+    }
+
+    /// Renders a type the way it is written in WIT.
+    fn wit_type_name(&self, ty: &Type) -> String {
+        match ty {
+            Type::Bool => "bool".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::U16 => "u16".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::U64 => "u64".to_string(),
+            Type::S8 => "s8".to_string(),
+            Type::S16 => "s16".to_string(),
+            Type::S32 => "s32".to_string(),
+            Type::S64 => "s64".to_string(),
+            Type::F32 => "f32".to_string(),
+            Type::F64 => "f64".to_string(),
+            Type::Char => "char".to_string(),
+            Type::String => "string".to_string(),
+            Type::ErrorContext => "error-context".to_string(),
+            Type::Id(type_id) => {
+                let type_def = &self.resolve.types[*type_id];
+                if let Some(name) = &type_def.name {
+                    return name.clone();
+                }
+                match &type_def.kind {
+                    TypeDefKind::List(element) => format!("list<{}>", self.wit_type_name(element)),
+                    TypeDefKind::Option(some) => format!("option<{}>", self.wit_type_name(some)),
+                    TypeDefKind::Tuple(tuple) => format!(
+                        "tuple<{}>",
+                        tuple
+                            .types
+                            .iter()
+                            .map(|ty| self.wit_type_name(ty))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    TypeDefKind::Result(result) => match (&result.ok, &result.err) {
+                        (None, None) => "result".to_string(),
+                        (Some(ok), None) => format!("result<{}>", self.wit_type_name(ok)),
+                        (None, Some(err)) => format!("result<_, {}>", self.wit_type_name(err)),
+                        (Some(ok), Some(err)) => format!(
+                            "result<{}, {}>",
+                            self.wit_type_name(ok),
+                            self.wit_type_name(err)
+                        ),
+                    },
+                    TypeDefKind::Type(aliased) => self.wit_type_name(aliased),
+                    TypeDefKind::Stream(Some(element)) => {
+                        format!("stream<{}>", self.wit_type_name(element))
+                    }
+                    TypeDefKind::Future(Some(output)) => {
+                        format!("future<{}>", self.wit_type_name(output))
+                    }
+                    kind => kind.as_str().to_string(),
+                }
+            }
+        }
+    }
+
+    /// Renders a function declaration the way it is written in WIT, e.g.
+    /// `add: func(a: s32, b: s32) -> s32;`.
+    fn wit_signature(&self, function: &Function) -> String {
+        let params = function
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, self.wit_type_name(ty)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        match &function.result {
+            Some(result) => format!(
+                "{}: func({}) -> {};",
+                function.name,
+                params,
+                self.wit_type_name(result)
+            ),
+            None => format!("{}: func({});", function.name, params),
+        }
+    }
+
+    fn generate_param_extraction(
+        &self,
+        param_type: &Type,
+        name: &str,
+        func_name: &str,
+        i_literal: &proc_macro2::Literal,
+    ) -> Result<TokenStream> {
+        let param_name = format_ident!("{}", name);
+
+        match param_type {
+            Type::String => {
+                // Handle Arc<str> to String conversion for ergonomic API
+                Ok(quote! {
+                    let #param_name = if let Some(wasm_component_layer::Value::String(val)) =
+                        params.get(#i_literal).cloned()
+                    {
+                        val.to_string()
+                    } else {
+                        panic!("Invalid parameter type for parameter '{}' in function '{}', expected String", #name, #func_name);
+                    };
+                })
+            }
+            Type::Bool => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::Bool(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected Bool", #name, #func_name);
+                };
+            }),
+            Type::S8 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::S8(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected S8", #name, #func_name);
+                };
+            }),
+            Type::U8 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::U8(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected U8", #name, #func_name);
+                };
+            }),
+            Type::S16 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::S16(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected S16", #name, #func_name);
+                };
+            }),
+            Type::U16 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::U16(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected U16", #name, #func_name);
+                };
+            }),
+            Type::S32 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::S32(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected S32", #name, #func_name);
+                };
+            }),
+            Type::U32 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::U32(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected U32", #name, #func_name);
+                };
+            }),
+            Type::S64 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::S64(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected S64", #name, #func_name);
+                };
+            }),
+            Type::U64 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::U64(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected U64", #name, #func_name);
+                };
+            }),
+            Type::F32 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::F32(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected F32", #name, #func_name);
+                };
+            }),
+            Type::F64 => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::F64(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected F64", #name, #func_name);
+                };
+            }),
+            Type::Char => Ok(quote! {
+                let #param_name = if let Some(wasm_component_layer::Value::Char(val)) =
+                    params.get(#i_literal).cloned()
+                {
+                    val
+                } else {
+                    panic!("Invalid parameter type for parameter '{}' in function '{}', expected Char", #name, #func_name);
+                };
+            }),
+            Type::Id(type_id) => {
+                let type_def = &self.resolve.types[*type_id];
+                match &type_def.kind {
+                    TypeDefKind::List(_) => {
+                        // Placeholder for List handling
+                        Ok(quote! {
+                            let #param_name = if let Some(wasm_component_layer::Value::List(val)) =
+                                params.get(#i_literal).cloned()
+                            {
+                                // TODO: Implement proper List conversion
+                                unimplemented!("List parameter conversion not yet implemented for parameter '{}' in function '{}'", #name, #func_name)
+                            } else {
+                                panic!("Invalid parameter type for parameter '{}' in function '{}', expected List", #name, #func_name);
+                            };
+                        })
+                    }
+                    TypeDefKind::Option(_) => {
+                        // Placeholder for Option handling
+                        Ok(quote! {
+                            let #param_name = if let Some(wasm_component_layer::Value::Option(val)) =
+                                params.get(#i_literal).cloned()
+                            {
+                                // TODO: Implement proper Option conversion
+                                unimplemented!("Option parameter conversion not yet implemented for parameter '{}' in function '{}'", #name, #func_name)
+                            } else {
+                                panic!("Invalid parameter type for parameter '{}' in function '{}', expected Option", #name, #func_name);
+                            };
+                        })
+                    }
+                    _ => {
+                        // Placeholder for other complex types (Record, Variant, etc.)
+                        Ok(quote! {
+                            let #param_name = if let Some(wasm_component_layer::Value::Record(val)) =
+                                params.get(#i_literal).cloned()
+                            {
+                                // TODO: Implement proper Record/complex type conversion
+                                unimplemented!("Complex type parameter conversion not yet implemented for parameter '{}' in function '{}'", #name, #func_name)
+                            } else {
+                                panic!("Invalid parameter type for parameter '{}' in function '{}', expected complex type", #name, #func_name);
+                            };
+                        })
+                    }
+                }
+            }
+            _ => {
+                // Fallback for any other unsupported types
+                Ok(quote! {
+                    let #param_name = {
+                        // TODO: Implement conversion for unsupported type
+                        unimplemented!("Unsupported parameter type conversion for parameter '{}' in function '{}'", #name, #func_name)
+                    };
+                })
+            }
+        }
+    }
+
+    /// Builds the `InterfaceIdentifier` expression for an interface, using the package the
+    /// interface was declared in, which is not necessarily the package of the world.
+    fn interface_identifier(&self, interface: &Interface) -> Result<TokenStream> {
+        let package_id = interface
+            .package
+            .context("Interface does not belong to a package")?;
+        let package_name = &self.resolve.packages[package_id].name;
+        let interface_name = interface
+            .name
+            .as_ref()
+            .context("Interface is missing a name")?;
+
+        let namespace = &package_name.namespace;
+        let pkg_name = &package_name.name;
+        let version = match &package_name.version {
+            Some(version) => {
+                let major = version.major;
+                let minor = version.minor;
+                let patch = version.patch;
+                let pre = if version.pre.is_empty() {
+                    quote! { semver::Prerelease::EMPTY }
+                } else {
+                    let pre = version.pre.as_str();
+                    quote! { semver::Prerelease::new(#pre).unwrap() }
+                };
+                let build = if version.build.is_empty() {
+                    quote! { semver::BuildMetadata::EMPTY }
+                } else {
+                    let build = version.build.as_str();
+                    quote! { semver::BuildMetadata::new(#build).unwrap() }
+                };
+                quote! {
+                    Some(semver::Version {
+                        major: #major,
+                        minor: #minor,
+                        patch: #patch,
+                        pre: #pre,
+                        build: #build,
+                    })
+                }
+            }
+            None => quote! { None },
+        };
+
+        Ok(quote! {
+            wasm_component_layer::InterfaceIdentifier::new(
+                wasm_component_layer::PackageIdentifier::new(
+                    wasm_component_layer::PackageName::new(#namespace, #pkg_name),
+                    #version,
+                ),
+                #interface_name,
+            )
+        })
+    }
+
+    /// Renders `namespace:package/interface`, the way imports are named in error messages.
+    /// Interfaces declared inline in the world only have their plain name.
+    fn interface_path(&self, key: &WorldKey) -> Result<String> {
+        let interface_id = match key {
+            WorldKey::Name(name) => return Ok(name.clone()),
+            WorldKey::Interface(interface_id) => interface_id,
+        };
+        let interface = &self.resolve.interfaces[*interface_id];
+        let package_id = interface
+            .package
+            .context("Interface does not belong to a package")?;
+        let package_name = &self.resolve.packages[package_id].name;
+        let interface_name = interface
+            .name
+            .as_ref()
+            .context("Interface is missing a name")?;
+        Ok(format!(
+            "{}:{}/{}",
+            package_name.namespace, package_name.name, interface_name
+        ))
+    }
+
+    fn get_interface_name(&self, key: &WorldKey) -> String {
+        match key {
+            WorldKey::Name(name) => name.clone(),
+            WorldKey::Interface(interface_id) => {
+                // Try to get a reasonable name from the interface
+                self.resolve.interfaces[*interface_id]
+                    .name
+                    .as_ref()
+                    .cloned()
+                    .unwrap_or_else(|| format!("interface_{}", interface_id.index()))
+            }
+        }
+    }
+
+    fn generate_instantiate_function(
+        &self,
+        _import_fields: &[TokenStream],
+        _export_fields: &[TokenStream],
+    ) -> Result<TokenStream> {
+        let mut import_definitions = Vec::new();
+        let mut export_initializations = Vec::new();
+        let mut import_id_fields = Vec::new();
+        let mut import_id_inits = Vec::new();
+        let mut export_id_fields = Vec::new();
+        let mut export_id_inits = Vec::new();
+
+        // Process imports, counting interfaces the way `world_imports` lists them
+        let import_interfaces = self
+            .world
+            .imports
+            .iter()
+            .filter(|(_, item)| matches!(item, WorldItem::Interface { .. }));
+        for (interface_index, (key, item)) in import_interfaces.enumerate() {
+            if let WorldItem::Interface { id, .. } = item {
+                let interface = &self.resolve.interfaces[*id];
+                let interface_name = self.get_interface_name(key);
+                let field_name = format_ident!("{}", interface_name.to_lowercase());
+
+                let mut function_definitions = Vec::new();
+
+                for (function_index, (func_name, function)) in
+                    interface.functions.iter().enumerate()
+                {
+                    // Goes through the fallible `try_*` method, an error traps the guest
+                    let try_ident = format_ident!("try_{}", func_name);
+                    let param_names: Vec<_> = function
+                        .params
+                        .iter()
+                        .map(|(name, _)| format_ident!("{}", name))
+                        .collect();
+
+                    // Create a series of statements to extract parameters, not using repetition
+                    let mut extract_params_stmts = Vec::new();
+                    for (i, (name, param_type)) in function.params.iter().enumerate() {
+                        let i_literal = proc_macro2::Literal::usize_unsuffixed(i);
+                        let extraction_code = self
+                            .generate_param_extraction(param_type, name, func_name, &i_literal)?;
+
+                        extract_params_stmts.push(extraction_code);
+                    } // Pass the field_name (like "console") to the closure for better context
+
+                    let interface_path = self.interface_path(key)?;
+
+                    // A panicking host function traps the guest instead of unwinding
+                    // through the runtime
+                    let host_call = if self.options.async_mode {
+                        // Export calls run on the blocking pool, where the runtime's handle
+                        // may block on the import
+                        quote! {
+                            tokio::runtime::Handle::current()
+                                .block_on(guard.#try_ident(#(#param_names),*))
+                        }
+                    } else {
+                        quote! { guard.#try_ident(#(#param_names),*) }
+                    };
+                    let call = quote! {
+                        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            #host_call
+                        }))
+                        .map_err(panic_to_error)??
+                    };
+                    let call_and_store_result = if function.result.is_some() {
+                        quote! {
+                            let result = #call;
+                            results[0] = wasm_component_layer::ComponentType::into_value(result)?;
+                        }
+                    } else {
+                        quote! {
+                            #call;
+                        }
+                    };
+
+                    let lock_interceptor =
+                        self.shared_lock(self.captured(quote! { interceptor_clone }));
+                    let lock_import = self.shared_lock(self.captured(quote! { field_name_clone }));
+                    let mut body = quote! {
+                        if let Some(interceptor) = #lock_interceptor.as_mut() {
+                            interceptor.before_call(caller.data(), #interface_path, #func_name, params)?;
+                        }
+
+                        // Extract parameters
+                        #(#extract_params_stmts)*
+
+                        // Call the function
+                        let mut guard = #lock_import;
+                        #call_and_store_result
+                        drop(guard);
+
+                        if let Some(interceptor) = #lock_interceptor.as_mut() {
+                            interceptor.after_call(caller.data(), #interface_path, #func_name, params, results)?;
+                        }
+
+                        Ok(())
+                    };
+                    if self.options.tracing {
+                        body = self.traced_call(
+                            "import",
+                            &interface_path,
+                            func_name,
+                            quote! { ?params },
+                            quote! { (|| -> anyhow::Result<()> { #body })() },
+                            quote! { Ok(()) => span.record("result", tracing::field::debug(&results)) },
+                        );
+                    }
+
+                    let capture_import = self.capture(quote! { #field_name.clone() });
+                    let capture_interceptor = self.capture(quote! { interceptor.clone() });
+                    let trampoline = quote! {
+                        let field_name_clone = #capture_import;
+                        let interceptor_clone = #capture_interceptor;
+                        wasm_component_layer::Func::new(
+                            &mut store,
+                            func_type,
+                            move |caller, params, results| {
+                                #body
+                            },
+                        )
+                    };
+                    let func = if self.options.optional_imports {
+                        let message = format!(
+                            "import `{}#{}` not provided by host",
+                            interface_path, func_name
+                        );
+                        quote! {
+                            match &#field_name {
+                                Some(#field_name) => { #trampoline }
+                                None => wasm_component_layer::Func::new(
+                                    &mut store,
+                                    func_type,
+                                    |_caller, _params, _results| Err(anyhow::anyhow!(#message)),
+                                ),
+                            }
+                        }
+                    } else {
+                        quote! {{ #trampoline }}
+                    };
+
+                    function_definitions.push(quote! {
+                        let func_type = world_imports()[#interface_index].functions[#function_index]
+                            .ty
+                            .clone();
+                        let func = #func;
+                        interface_instance.define_func(#func_name, func)?;
+                    });
+                }
+
+                let shared_import = if self.options.optional_imports {
+                    let shared = self.shared_new(quote! { import });
+                    quote! {
+                        imports.#field_name.map(|import| #shared)
+                    }
+                } else {
+                    self.shared_new(quote! { imports.#field_name })
+                };
+
+                // Interfaces declared inline in the world have no identifier, their functions
+                // are imported on the root
+                let WorldKey::Interface(_) = key else {
+                    import_definitions.push(quote! {
+                        let #field_name = #shared_import;
+                        let interface_instance = linker.root_mut();
+
+                        #(#function_definitions)*
+                    });
+                    continue;
+                };
+
+                let interface_id = self.interface_identifier(interface)?;
+                import_id_fields.push(quote! {
+                    #field_name: Vec<wasm_component_layer::InterfaceIdentifier>
+                });
+                // The component may import a newer, semver compatible version of the interface
+                import_id_inits.push(quote! {
+                    #field_name: compatible_imports(component, &#interface_id)
+                });
+
+                import_definitions.push(quote! {
+                    let #field_name = #shared_import;
+
+                    for interface_id in &self.import_ids.#field_name {
+                        let interface_instance = linker.define_instance(interface_id.clone())?;
+
+                        #(#function_definitions)*
+                    }
+                });
+            }
+        }
+
+        // Process exports
+        for (key, item) in &self.world.exports {
+            if let WorldItem::Interface { id, .. } = item {
+                let interface = &self.resolve.interfaces[*id];
+                let interface_name = self.get_interface_name(key);
+                let field_name = format_ident!("{}", interface_name.to_lowercase());
+                let impl_name = format_ident!("{}Impl", interface_name);
+
+                // Generate field initializations dynamically based on the interface functions
+                let mut field_inits = Vec::new();
+                for (func_name, _function) in &interface.functions {
+                    let func_field = format_ident!("{}", func_name);
+                    field_inits.push(quote! {
+                        #func_field: interface_instance
+                            .func(#func_name)
+                            .with_context(|| format!("`{}` does not export `{}`", interface_id, #func_name))?
+                            .typed()?
+                    });
+                }
+
+                // Interfaces declared inline in the world are exported on the root
+                let find_instance = match key {
+                    WorldKey::Name(name) => quote! {
+                        let interface_id = #name;
+                        let interface_instance = instance.exports().root();
+                    },
+                    WorldKey::Interface(_) => {
+                        let interface_id = self.interface_identifier(interface)?;
+                        export_id_fields.push(quote! {
+                            #field_name: wasm_component_layer::InterfaceIdentifier
+                        });
+                        export_id_inits.push(quote! {
+                            #field_name: compatible_export(component, &#interface_id)
+                        });
+
+                        quote! {
+                            let interface_id = &self.export_ids.#field_name;
+                            let interface_instance = instance
+                                .exports()
+                                .instance(interface_id)
+                                .with_context(|| format!("Component does not export `{}`", interface_id))?;
+                        }
+                    }
+                };
+
+                export_initializations.push(quote! {
+                    #find_instance
+
+                    // Create the implementation
+                    let #field_name = #impl_name {
+                        store: store_arc.clone(),
+                        #(#field_inits),*
+                    };
+                });
+            }
+        }
+
+        // Create proper field initializers from field names
+        let mut export_fields_init = Vec::new();
+        let async_bounds = self.async_bounds();
+        let shared_interceptor = self.shared_new(quote! { interceptor });
+        let shared_store = self.shared_new(quote! { store });
+        let interceptor_bound = if self.options.single_threaded {
+            quote! { 'static }
+        } else {
+            quote! { Send }
+        };
+        let single_threaded = if self.options.single_threaded {
+            quote! {
+                /// Lets state that isn't `Send` be captured by host functions, which the runtime
+                /// requires to be `Send + Sync`. Only the thread that created it may access it,
+                /// other threads panic on access and leak it on drop.
+                struct SingleThreaded<T> {
+                    value: std::mem::ManuallyDrop<T>,
+                    thread: std::thread::ThreadId,
+                }
+
+                // Safety: the value is only ever touched on the thread that created it
+                unsafe impl<T> Send for SingleThreaded<T> {}
+                unsafe impl<T> Sync for SingleThreaded<T> {}
+
+                impl<T> SingleThreaded<T> {
+                    fn new(value: T) -> Self {
+                        Self {
+                            value: std::mem::ManuallyDrop::new(value),
+                            thread: std::thread::current().id(),
+                        }
+                    }
+
+                    fn get(&self) -> &T {
+                        assert!(
+                            std::thread::current().id() == self.thread,
+                            "single threaded bindings used from another thread"
+                        );
+                        &self.value
+                    }
+                }
+
+                impl<T> Drop for SingleThreaded<T> {
+                    fn drop(&mut self) {
+                        if std::thread::current().id() == self.thread {
+                            // Safety: the value is never used after this
+                            unsafe { std::mem::ManuallyDrop::drop(&mut self.value) }
+                        }
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        // The guest may call imports while it initialises, so in async mode it runs on
+        // tokio's blocking pool like export calls
+        let (asyncness, await_instance, instantiate_component) = if self.options.async_mode {
+            (
+                quote! { async },
+                quote! { .await },
+                quote! {
+                    let component = self.component.clone();
+                    let (store, instance) = tokio::task::spawn_blocking(move || {
+                        let instance = linker.instantiate(&mut store, &component);
+                        (store, instance)
+                    })
+                    .await?;
+                    let instance = instance?;
+                },
+            )
+        } else {
+            (
+                quote! {},
+                quote! {},
+                quote! {
+                    let instance = linker.instantiate(&mut store, &self.component)?;
+                },
+            )
+        };
+
+        for (key, item) in &self.world.exports {
+            if let WorldItem::Interface { .. } = item {
+                let interface_name = self.get_interface_name(key);
+                let field_name = format_ident!("{}", interface_name.to_lowercase());
+
+                export_fields_init.push(quote! {
+                    #field_name: Box::new(#field_name)
+                });
+            }
+        }
+
+        Ok(quote! {
+            struct ImportIds {
+                #(#import_id_fields,)*
+            }
+
+            struct ExportIds {
+                #(#export_id_fields,)*
+            }
+
+            /// A component that has been checked against the world, ready to be instantiated
+            /// many times.
+            ///
+            /// [`Pre::new`] does the type check and resolves the semver compatible interfaces
+            /// once, the function types come from [`world_imports`]. The linker is not cached:
+            /// import functions are bound to the store of an instance and capture its `Imports`,
+            /// so every instantiation defines them on a fresh linker.
+            pub struct Pre {
+                component: wasm_component_layer::Component,
+                import_ids: ImportIds,
+                export_ids: ExportIds,
+                limits: Limits,
+                // Shared with the exports of every instance, which give their slot back on drop
+                live_instances: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+            }
+
+            /// Limits on the instances created through a [`Pre`]. Nothing is limited by default.
+            #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+            pub struct Limits {
+                /// How many instances may be alive at once. An instance counts until its
+                /// `Exports` are dropped or turned back into the store.
+                pub max_instances: Option<usize>,
+            }
+
+            /// Returned by [`Pre::instantiate`] when an instance would exceed the [`Limits`].
+            #[derive(Debug, Clone, PartialEq, Eq)]
+            pub enum LimitExceeded {
+                Instances { limit: usize },
+            }
+
+            impl std::fmt::Display for LimitExceeded {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    match self {
+                        LimitExceeded::Instances { limit } => {
+                            write!(f, "more than {} instances of the component are alive", limit)
+                        }
+                    }
+                }
+            }
+
+            impl std::error::Error for LimitExceeded {}
+
+            /// Counts an instance against [`Limits::max_instances`] while it is alive.
+            struct InstanceSlot(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+            impl InstanceSlot {
+                fn acquire(
+                    live_instances: &std::sync::Arc<std::sync::atomic::AtomicUsize>,
+                    limits: &Limits,
+                ) -> std::result::Result<Self, LimitExceeded> {
+                    use std::sync::atomic::Ordering;
+
+                    live_instances
+                        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                            match limits.max_instances {
+                                Some(limit) if count >= limit => None,
+                                _ => Some(count + 1),
+                            }
+                        })
+                        .map_err(|_| LimitExceeded::Instances {
+                            limit: limits.max_instances.unwrap_or_default(),
+                        })?;
+                    Ok(Self(live_instances.clone()))
+                }
+            }
+
+            impl Drop for InstanceSlot {
+                fn drop(&mut self) {
+                    self.0.fetch_sub(1, std::sync::atomic::Ordering::AcqRel);
+                }
+            }
+
+            impl Pre {
+                /// Checks `component` against the world and resolves its interfaces.
+                pub fn new(component: &wasm_component_layer::Component) -> anyhow::Result<Self> {
+                    // Fail with a readable diff instead of deep inside the linker
+                    check_compatibility(component)?;
+
+                    Ok(Self {
+                        component: component.clone(),
+                        import_ids: ImportIds {
+                            #(#import_id_inits,)*
+                        },
+                        export_ids: ExportIds {
+                            #(#export_id_inits,)*
+                        },
+                        limits: Limits::default(),
+                        live_instances: Default::default(),
+                    })
+                }
+
+                /// Enforces `limits` on the instances created from now on.
+                pub fn with_limits(mut self, limits: Limits) -> Self {
+                    self.limits = limits;
+                    self
+                }
+
+                pub fn component(&self) -> &wasm_component_layer::Component {
+                    &self.component
+                }
+
+                pub #asyncness fn instantiate<T, E>(
+                    &self,
+                    mut store: wasm_component_layer::Store<T, E>,
+                    imports: impl Into<InterceptedImports<T>>,
+                ) -> anyhow::Result<Exports<T, E>>
+                where
+                    E: wasm_runtime_layer::backend::WasmEngine,
+                    T: 'static,
+                    #async_bounds
+                {
+                    use anyhow::Context;
+
+                    let instance_slot = InstanceSlot::acquire(&self.live_instances, &self.limits)?;
+                    let InterceptedImports { imports, interceptor } = imports.into();
+                    let interceptor = #shared_interceptor;
+                    let mut linker = wasm_component_layer::Linker::default();
+
+                    // Set up imports in the linker
+                    #(#import_definitions)*
+
+                    // Instantiate the component
+                    #instantiate_component
+
+                    // Extract exports and create implementations
+                    let store_arc = #shared_store;
+                    #(#export_initializations)*
+
+                    // Return the exports struct
+                    Ok(Exports {
+                        #(#export_fields_init,)*
+                        store: store_arc,
+                        instance,
+                        instance_slot,
+                    })
+                }
+            }
+
+            /// Runs around every import call the guest makes, e.g. to validate arguments, check
+            /// permissions against the store data or redact results.
+            ///
+            /// Returning an error traps the guest. Wrap the imports with
+            /// [`Imports::with_interceptor`] to install one.
+            pub trait Interceptor<T>: #interceptor_bound {
+                /// Runs before the import is called. `interface` is written as
+                /// `namespace:package/interface`, or just the name of an interface declared
+                /// inline in the world.
+                fn before_call(
+                    &mut self,
+                    data: &T,
+                    interface: &str,
+                    function: &str,
+                    args: &[wasm_component_layer::Value],
+                ) -> anyhow::Result<()> {
+                    let _ = (data, interface, function, args);
+                    Ok(())
+                }
+
+                /// Runs after the import returned, `results` may still be changed.
+                fn after_call(
+                    &mut self,
+                    data: &T,
+                    interface: &str,
+                    function: &str,
+                    args: &[wasm_component_layer::Value],
+                    results: &mut [wasm_component_layer::Value],
+                ) -> anyhow::Result<()> {
+                    let _ = (data, interface, function, args, results);
+                    Ok(())
+                }
+            }
+
+            /// [`Imports`] together with the [`Interceptor`] wrapping all of them.
+            pub struct InterceptedImports<T> {
+                imports: Imports,
+                interceptor: Option<Box<dyn Interceptor<T>>>,
+            }
+
+            impl<T> From<Imports> for InterceptedImports<T> {
+                fn from(imports: Imports) -> Self {
+                    Self {
+                        imports,
+                        interceptor: None,
+                    }
+                }
+            }
+
+            impl Imports {
+                pub fn with_interceptor<T>(
+                    self,
+                    interceptor: impl Interceptor<T> + 'static,
+                ) -> InterceptedImports<T> {
+                    InterceptedImports {
+                        imports: self,
+                        interceptor: Some(Box::new(interceptor)),
+                    }
+                }
+            }
+
+            #single_threaded
+
+            /// Turns the payload of a panic in a host function into the error the guest traps with.
+            fn panic_to_error(payload: Box<dyn std::any::Any + Send>) -> anyhow::Error {
+                match payload.downcast::<String>() {
+                    Ok(message) => anyhow::anyhow!(*message),
+                    Err(payload) => match payload.downcast::<&'static str>() {
+                        Ok(message) => anyhow::anyhow!(*message),
+                        Err(_) => anyhow::anyhow!("host function panicked"),
+                    },
+                }
+            }
+
+            /// Checks and instantiates `component` in one go. The check runs on every call, use
+            /// [`Pre`] to instantiate the same component repeatedly.
+            pub #asyncness fn instantiate<T, E>(
+                store: wasm_component_layer::Store<T, E>,
+                component: &wasm_component_layer::Component,
+                imports: impl Into<InterceptedImports<T>>,
+            ) -> anyhow::Result<Exports<T, E>>
+            where
+                E: wasm_runtime_layer::backend::WasmEngine,
+                T: 'static,
+                #async_bounds
+            {
+                Pre::new(component)?.instantiate(store, imports)#await_instance
+            }
+        })
+    }
+}
+
+/// Turns a WIT identifier like `console` or `get-value` into `Console` or `GetValue`.
+fn to_upper_camel_case(name: &str) -> String {
+    name.split(['-', '_'])
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
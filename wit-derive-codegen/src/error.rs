@@ -0,0 +1,170 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::CharIndices;
+
+/// The part of the input an error is reported at.
+#[derive(Debug, Clone, Copy)]
+pub enum Location {
+    /// The value of a key in the macro input, e.g. `world`.
+    Key(&'static str),
+    /// A 1-based line and column inside the `inline` WIT source. Like in wit-parser's
+    /// messages, the column counts bytes.
+    Inline { line: usize, column: usize },
+}
+
+/// An error that knows where in the input it should be reported.
+#[derive(Debug)]
+pub struct LocatedError {
+    pub location: Location,
+    pub message: String,
+}
+
+impl LocatedError {
+    pub fn new(location: Location, message: impl Into<String>) -> Self {
+        Self {
+            location,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LocatedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl std::error::Error for LocatedError {}
+
+/// Wraps a WIT parse or resolve error of the inline source, so it points at the position
+/// wit-parser reported inside the string literal.
+pub fn inline_wit_error(err: anyhow::Error) -> anyhow::Error {
+    let message = render(&err);
+    match find_wit_location(&message, "inline.wit") {
+        Some((line, column)) => LocatedError::new(Location::Inline { line, column }, message).into(),
+        None => LocatedError::new(Location::Key("inline"), message).into(),
+    }
+}
+
+/// Finds the character at a [`Location::Inline`] position in the source text of the string
+/// literal holding the inline WIT, everything between its quotes, and returns its byte range
+/// there. Escapes of non-raw literals are followed, so the range of an escaped character covers
+/// its whole escape sequence.
+pub fn inline_source_range(
+    source: &str,
+    raw: bool,
+    line: usize,
+    column: usize,
+) -> Option<Range<usize>> {
+    // Line and column of the next character of the literal's value
+    let (mut value_line, mut value_column) = (1, 1);
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let value = if raw || c != '\\' {
+            Some(c)
+        } else {
+            unescape(&mut chars)?
+        };
+        let Some(value) = value else {
+            continue;
+        };
+        let end = chars.peek().map_or(source.len(), |(i, _)| *i);
+        if (value_line, value_column) == (line, column) {
+            return Some(start..end);
+        }
+        if value == '\n' {
+            value_line += 1;
+            value_column = 1;
+        } else {
+            value_column += value.len_utf8();
+        }
+    }
+
+    // Errors at the end of the input point right behind the last character
+    ((value_line, value_column) == (line, column)).then_some(source.len()..source.len())
+}
+
+/// Reads the escape sequence following a `\` in a string literal and returns the character it
+/// stands for, or `None` for a line continuation.
+fn unescape(chars: &mut Peekable<CharIndices>) -> Option<Option<char>> {
+    let (_, c) = chars.next()?;
+    let value = match c {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        'x' => {
+            let digits: String = [chars.next()?.1, chars.next()?.1].iter().collect();
+            char::from(u8::from_str_radix(&digits, 16).ok()?)
+        }
+        'u' => {
+            let mut digits = String::new();
+            for (_, c) in chars.by_ref() {
+                match c {
+                    '{' => {}
+                    '}' => break,
+                    c => digits.push(c),
+                }
+            }
+            char::from_u32(u32::from_str_radix(&digits.replace('_', ""), 16).ok()?)?
+        }
+        '\n' => {
+            // The newline and the indentation of the next line are not part of the value
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+            return Some(None);
+        }
+        c => c,
+    };
+    Some(Some(value))
+}
+
+/// Formats an error with its chain of causes, one per line, without the backtrace `{:?}`
+/// would attach.
+pub fn render(err: &anyhow::Error) -> String {
+    let mut message = err.to_string();
+    for cause in err.chain().skip(1) {
+        message.push_str(&format!("\n  caused by: {}", cause));
+    }
+    message
+}
+
+/// Extracts the line and column of a `--> file:line:col` marker rendered by wit-parser.
+fn find_wit_location(message: &str, file: &str) -> Option<(usize, usize)> {
+    message.lines().find_map(|line| {
+        let location = line.trim_start().strip_prefix("--> ")?;
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next()?.parse().ok()?;
+        let line = parts.next()?.parse().ok()?;
+        (parts.next()? == file).then_some((line, column))
+    })
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be a likely typo.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= candidate.len().max(name.len()) / 3 + 1)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,450 @@
+//! Code generation behind `wit_derive::generate!`, usable from build scripts and tools.
+//!
+//! ```rust,ignore
+//! wit_derive_codegen::Opts::new()
+//!     .world("calculator")
+//!     .path("wit")
+//!     .generate_to_file(out_dir.join("bindings.rs"))?;
+//! ```
+//!
+//! The written file is formatted with `prettyplease` and can be pulled in with
+//! `include!`, or checked in.
+
+use anyhow::{Context, Result};
+use proc_macro2::TokenStream;
+use std::path::{Path, PathBuf};
+
+pub mod codegen;
+pub mod error;
+
+use crate::codegen::{CodeGenerator, Options, WitSourceContent};
+use crate::error::{LocatedError, Location};
+use wit_parser::{PackageId, Resolve, UnresolvedPackageGroup, WorldId};
+
+/// Options for generating bindings, mirroring the keys of `wit_derive::generate!`.
+///
+/// This is where the options are documented. The macro key and the `wit-derive` flag of an
+/// option share the name of its method, except for `async` and `threading: "single"`.
+#[derive(Debug, Default)]
+pub struct Opts {
+    world: Option<String>,
+    paths: Vec<PathBuf>,
+    inline: Option<String>,
+    base_dir: Option<PathBuf>,
+    options: Options,
+}
+
+impl Opts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The world to generate bindings for, either its name in the main package or a fully
+    /// qualified `namespace:package/world@version`. Can be left out when the main package
+    /// defines a single world.
+    pub fn world(mut self, world: impl Into<String>) -> Self {
+        self.world = Some(world.into());
+        self
+    }
+
+    /// A directory, `.wit` file or `.wasm` encoded WIT package. Can be given more than once,
+    /// the first path holds the main package and the others are resolved beforehand as
+    /// dependencies.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// WIT source holding the main package. Any paths are resolved first, so it can `include`
+    /// or `import` what they define.
+    pub fn inline(mut self, wit: impl Into<String>) -> Self {
+        self.inline = Some(wit.into());
+        self
+    }
+
+    /// The directory relative paths are resolved against, the working directory by default.
+    pub fn base_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(dir.into());
+        self
+    }
+
+    /// Generate a `Recording*` type for every imported interface, e.g. `RecordingConsole`.
+    /// It records each call with its arguments and answers with a canned value, a closure or
+    /// `Default::default()`, which is handy in tests:
+    /// ```rust,ignore
+    /// let console = calculator::RecordingConsole::default();
+    /// let imports = calculator::Imports {
+    ///     console: Box::new(console.clone()),
+    /// };
+    /// // ... instantiate and call into the guest ...
+    /// assert_eq!(
+    ///     console.calls(),
+    ///     vec![calculator::ConsoleCall::Print { line: "hello".to_string() }]
+    /// );
+    /// ```
+    pub fn stubs(mut self, enabled: bool) -> Self {
+        self.options.stubs = enabled;
+        self
+    }
+
+    /// Make every field of `Imports` an `Option`. Interfaces left as `None` are still linked,
+    /// but trap with "import `namespace:package/interface#function` not provided by host" when
+    /// the guest calls them. `Imports::missing_imports()` lists them.
+    pub fn optional_imports(mut self, enabled: bool) -> Self {
+        self.options.optional_imports = enabled;
+        self
+    }
+
+    /// Make import and export methods return a `BoxFuture`, and `instantiate`, `call_dynamic`
+    /// and the functions returned by `forwarder()` async. They run the guest on tokio's
+    /// blocking pool and drive the import futures from there with `Handle::block_on`, so the
+    /// crate using the bindings has to depend on `tokio` with the `rt` feature and call
+    /// exports from within a runtime. `async` in the macro.
+    pub fn async_mode(mut self, enabled: bool) -> Self {
+        self.options.async_mode = enabled;
+        self
+    }
+
+    /// Share state through `Rc<RefCell<..>>` instead of `Arc<Mutex<..>>` and don't require
+    /// imports to be `Send`, e.g. to hold `Rc`-based UI state on `wasm32-unknown-unknown`. The
+    /// bindings panic when used from another thread than the one that instantiated the
+    /// component. `threading: "single"` in the macro.
+    pub fn single_threaded(mut self, enabled: bool) -> Self {
+        self.options.single_threaded = enabled;
+        self
+    }
+
+    /// Generate `stub_component(bodies)`, which builds a component of the world from
+    /// `wit_component::dummy_module`, so hosts can be tested without a wasm32 toolchain.
+    /// Exports trap unless given a body of `wasm_encoder` instructions, and the crate using the
+    /// bindings has to depend on `wit-component` with the `dummy-module` feature, `wasmparser`
+    /// and `wasm-encoder`.
+    pub fn stub_component(mut self, enabled: bool) -> Self {
+        self.options.stub_component = enabled;
+        self
+    }
+
+    /// Generate `wit::resolve()`, which decodes `wit::ENCODED_PACKAGE` into a
+    /// `wit_parser::Resolve`. The crate using the bindings has to depend on `wit-parser`, in a
+    /// version compatible with the one the bindings are generated with.
+    pub fn resolve(mut self, enabled: bool) -> Self {
+        self.options.resolve = enabled;
+        self
+    }
+
+    /// Run every import and export call in an `info` span carrying the interface, the function
+    /// and its arguments. The result or error and the duration are recorded on the span when
+    /// the call returns. The crate using the bindings has to depend on `tracing`.
+    pub fn tracing(mut self, enabled: bool) -> Self {
+        self.options.tracing = enabled;
+        self
+    }
+
+    /// Embed the content of the WIT files instead of `include_str!`-ing their absolute path,
+    /// for bindings that are checked in and built on other machines.
+    pub fn embed_sources(mut self, enabled: bool) -> Self {
+        self.options.embed_sources = enabled;
+        self
+    }
+
+    /// Generates the bindings as tokens.
+    pub fn generate(self) -> Result<TokenStream> {
+        if self.options.async_mode && self.options.single_threaded {
+            return Err(LocatedError::new(
+                Location::Key("threading"),
+                "Single threaded bindings can't be async, export calls run on another thread",
+            )
+            .into());
+        }
+
+        let base_dir = self.base_dir.unwrap_or_default();
+        let mut resolve = Resolve::new();
+        let mut main_package = None;
+
+        // Parse the WIT files and collect them, the generated code tracks them as build inputs
+        let files = match self.paths.split_first() {
+            Some((main_path, dep_paths)) => {
+                // Dependencies have to be known before the main package can refer to them,
+                // so push them first. Each directory also picks up its own `deps/` folder.
+                for dep_path in dep_paths {
+                    let dep_path = base_dir.join(dep_path);
+                    resolve
+                        .push_path(&dep_path)
+                        .with_context(|| {
+                            format!("Failed to resolve WIT dependency {}", dep_path.display())
+                        })
+                        .map_err(|e| {
+                            LocatedError::new(Location::Key("path"), error::render(&e))
+                        })?;
+                }
+                let main_path = base_dir.join(main_path);
+                let (pkg_id, _) = resolve
+                    .push_path(&main_path)
+                    .with_context(|| {
+                        format!("Failed to resolve WIT package in {}", main_path.display())
+                    })
+                    .map_err(|e| LocatedError::new(Location::Key("path"), error::render(&e)))?;
+                main_package = Some(pkg_id);
+
+                // Files of the main path are keyed relative to it, the others keep the given
+                // path. A single file dependency is keyed by that path alone.
+                let mut files = Vec::new();
+                for (i, path) in self.paths.iter().enumerate() {
+                    let wit_path = base_dir.join(path);
+                    let mut found = Vec::new();
+                    collect_wit_files(&wit_path, &wit_path, &mut found)?;
+                    for (relative, absolute) in found {
+                        let key = if i == 0 {
+                            relative
+                        } else if wit_path.is_file() {
+                            path.clone()
+                        } else {
+                            path.join(relative)
+                        };
+                        files.push((to_key(&key), absolute));
+                    }
+                }
+                files.sort();
+                files.dedup();
+
+                // A dependency path can name a file that the main path has at the same
+                // relative path, e.g. `deps/x/y.wit`
+                if let Some(pair) = files.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                    return Err(LocatedError::new(
+                        Location::Key("path"),
+                        format!(
+                            "{} and {} would both be listed as '{}' in wit::files",
+                            pair[0].1.display(),
+                            pair[1].1.display(),
+                            pair[0].0
+                        ),
+                    )
+                    .into());
+                }
+                Some(files)
+            }
+            None => None,
+        };
+
+        // Inline WIT comes last, so it can refer to the packages found on disk
+        if let Some(content) = &self.inline {
+            let pkg_id = UnresolvedPackageGroup::parse("inline.wit", content)
+                .and_then(|group| resolve.push_group(group))
+                .map_err(error::inline_wit_error)?;
+            main_package = Some(pkg_id);
+        }
+
+        let pkg_id = main_package.context("Either a path or inline WIT has to be given")?;
+        let world_id = find_world(&resolve, pkg_id, self.world.as_deref())?;
+        let wit_source = WitSourceContent {
+            files,
+            inline: self.inline,
+        };
+
+        let world = &resolve.worlds[world_id];
+        let package = &resolve.packages[world.package.unwrap()];
+
+        let mut generator = CodeGenerator::new(&resolve, package, world, wit_source, self.options);
+        generator.generate()
+    }
+
+    /// Generates the bindings as Rust source, formatted with `prettyplease`.
+    pub fn generate_string(self) -> Result<String> {
+        let tokens = self.generate()?;
+        let file: syn::File =
+            syn::parse2(tokens).context("Failed to parse the generated bindings")?;
+        Ok(format!(
+            "// Generated by wit-derive, do not edit.\n\n{}",
+            prettyplease::unparse(&file)
+        ))
+    }
+
+    /// Generates the bindings and writes them to `out`.
+    pub fn generate_to_file(self, out: impl AsRef<Path>) -> Result<()> {
+        let out = out.as_ref();
+        let source = self.generate_string()?;
+        std::fs::write(out, source)
+            .with_context(|| format!("Failed to write bindings to {}", out.display()))
+    }
+}
+
+/// Looks up a world by name in the main package, or by its fully qualified name anywhere in
+/// `resolve`. Without a name, the only world of the main package is picked.
+fn find_world(resolve: &Resolve, main_package: PackageId, name: Option<&str>) -> Result<WorldId> {
+    let Some(name) = name else {
+        let package = &resolve.packages[main_package];
+        let mut worlds = package.worlds.values();
+        return match (worlds.next(), worlds.next()) {
+            (Some(world_id), None) => Ok(*world_id),
+            (None, _) => Err(LocatedError::new(
+                Location::Key("world"),
+                format!("Package '{}' does not define any worlds", package.name),
+            )
+            .into()),
+            (Some(_), Some(_)) => Err(LocatedError::new(
+                Location::Key("world"),
+                format!(
+                    "Package '{}' defines several worlds, pick one with 'world': {}",
+                    package.name,
+                    quoted_list(package.worlds.keys().map(String::as_str))
+                ),
+            )
+            .into()),
+        };
+    };
+
+    // `namespace:package/world@version`
+    let (package_id, name) = match name.split_once('/') {
+        Some((package_name, world)) => {
+            let (world, version) = match world.split_once('@') {
+                Some((world, version)) => (world, Some(version)),
+                None => (world, None),
+            };
+            (find_package(resolve, package_name, version)?, world)
+        }
+        None => (main_package, name),
+    };
+
+    let package = &resolve.packages[package_id];
+    if let Some(world_id) = package.worlds.get(name) {
+        return Ok(*world_id);
+    }
+
+    let available: Vec<&str> = package.worlds.keys().map(String::as_str).collect();
+    let mut message = format!("World '{}' not found in package '{}'", name, package.name);
+    if available.is_empty() {
+        message.push_str(", the package does not define any worlds");
+    } else {
+        message.push_str(&format!(", available worlds: {}", quoted_list(available.iter().copied())));
+        if let Some(suggestion) = error::suggest(name, available.iter().copied()) {
+            message.push_str(&format!("\n\nhelp: did you mean '{}'?", suggestion));
+        }
+    }
+
+    Err(LocatedError::new(Location::Key("world"), message).into())
+}
+
+/// Finds the package `namespace:name`, with the given version if there are several.
+fn find_package(resolve: &Resolve, name: &str, version: Option<&str>) -> Result<PackageId> {
+    let candidates: Vec<_> = resolve
+        .packages
+        .iter()
+        .filter(|(_, package)| {
+            format!("{}:{}", package.name.namespace, package.name.name) == name
+                && version.is_none_or(|version| {
+                    package.name.version.as_ref().map(ToString::to_string).as_deref()
+                        == Some(version)
+                })
+        })
+        .collect();
+
+    match candidates.as_slice() {
+        [(id, _)] => Ok(*id),
+        [] => {
+            let name = match version {
+                Some(version) => format!("{}@{}", name, version),
+                None => name.to_string(),
+            };
+            let available: Vec<String> =
+                resolve.packages.iter().map(|(_, package)| package.name.to_string()).collect();
+            Err(LocatedError::new(
+                Location::Key("world"),
+                format!(
+                    "Package '{}' not found, available packages: {}",
+                    name,
+                    quoted_list(available.iter().map(String::as_str))
+                ),
+            )
+            .into())
+        }
+        _ => {
+            let versions: Vec<String> = candidates
+                .iter()
+                .filter_map(|(_, package)| package.name.version.as_ref())
+                .map(ToString::to_string)
+                .collect();
+            Err(LocatedError::new(
+                Location::Key("world"),
+                format!(
+                    "Several versions of package '{}' are known, add one to the world: {}",
+                    name,
+                    quoted_list(versions.iter().map(String::as_str))
+                ),
+            )
+            .into())
+        }
+    }
+}
+
+fn quoted_list<'a>(items: impl IntoIterator<Item = &'a str>) -> String {
+    items
+        .into_iter()
+        .map(|item| format!("'{}'", item))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Collects every WIT source below `dir`, together with `.wasm` encoded packages, so the
+/// generated code can register them with cargo as build inputs.
+///
+/// Each entry is the path relative to `root` and the absolute path of the file.
+fn collect_wit_files(root: &Path, dir: &Path, files: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    use std::fs;
+
+    if dir.is_file() {
+        if is_wit_source(dir) {
+            // A single file is its own root, so it is keyed by its file name
+            let filename = dir
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid file name"))?;
+            files.push((PathBuf::from(filename), dir.to_path_buf()));
+        }
+        return Ok(());
+    }
+
+    if !dir.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Path is neither a file nor a directory: {}",
+            dir.display()
+        ));
+    }
+
+    // Recursively collect all .wit files
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?
+    {
+        let entry = entry.with_context(|| "Failed to read directory entry")?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_wit_files(root, &path, files)?;
+        } else if is_wit_source(&path) {
+            let relative = path.strip_prefix(root)?.to_path_buf();
+            files.push((relative, path));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_wit_source(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("wit") | Some("wasm")
+    )
+}
+
+/// Turns a path into the `/` separated key used in `wit::files::FILES`. Parent segments are
+/// kept, so `../vendor/logging.wasm` and `vendor/logging.wasm` stay apart.
+fn to_key(path: &Path) -> String {
+    use std::path::Component;
+
+    path.components()
+        .filter_map(|component| match component {
+            Component::CurDir => None,
+            Component::RootDir => Some("".into()),
+            component => Some(component.as_os_str().to_string_lossy()),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,209 @@
+// Generating bindings through the library instead of the macro
+
+use wit_derive_codegen::Opts;
+use wit_derive_codegen::error::{LocatedError, Location};
+
+fn opts() -> Opts {
+    Opts::new()
+        .world("calculator")
+        .base_dir(env!("CARGO_MANIFEST_DIR"))
+        .path("tests/wit-deps")
+}
+
+#[test]
+fn test_generate_string_is_formatted() {
+    let source = opts().generate_string().unwrap();
+
+    assert!(source.starts_with("// Generated by wit-derive"));
+    assert!(source.contains("\npub mod calculator {\n"));
+    assert!(source.contains("pub trait console"));
+    // The WIT files are tracked through their absolute path by default
+    assert!(source.contains("include_str!("));
+}
+
+#[test]
+fn test_embed_sources() {
+    let source = opts().embed_sources(true).generate_string().unwrap();
+
+    assert!(!source.contains("include_str!("));
+    assert!(source.contains("package example:logging@0.2.0;"));
+}
+
+#[test]
+fn test_generate_to_file() {
+    let out = std::env::temp_dir().join("wit-derive-codegen-api-bindings.rs");
+    opts().embed_sources(true).generate_to_file(&out).unwrap();
+
+    let written = std::fs::read_to_string(&out).unwrap();
+    std::fs::remove_file(&out).unwrap();
+    assert_eq!(written, opts().embed_sources(true).generate_string().unwrap());
+}
+
+#[test]
+fn test_unknown_world() {
+    let err = opts().world("calculatr").generate().unwrap_err();
+
+    assert!(err.to_string().contains("did you mean 'calculator'?"));
+}
+
+#[test]
+fn test_unknown_qualified_world() {
+    let err = opts().world("example:missing/calculator").generate().unwrap_err();
+    assert!(err.to_string().starts_with("Package 'example:missing' not found"));
+
+    let err = opts().world("example:logging/calculator").generate().unwrap_err();
+    assert!(err.to_string().contains("not found in package 'example:logging@0.2.0'"));
+}
+
+#[test]
+fn test_dependency_keys_keep_parent_segments() {
+    let source = Opts::new()
+        .world("calculator")
+        .base_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/wit-split"))
+        .path("main")
+        .path("../wit-deps/deps/logging/log.wit")
+        .embed_sources(true)
+        .generate_string()
+        .unwrap();
+
+    assert!(source.contains("\"../wit-deps/deps/logging/log.wit\""));
+    assert!(!source.contains("\"wit-deps/"));
+}
+
+#[test]
+fn test_single_threaded_async_is_rejected() {
+    let err = opts()
+        .async_mode(true)
+        .single_threaded(true)
+        .generate()
+        .unwrap_err();
+
+    assert!(err.to_string().starts_with("Single threaded bindings can't be async"));
+}
+
+#[test]
+fn test_unsupported_types_name_their_function() {
+    let err = Opts::new()
+        .inline(
+            r#"
+            package example:shapes;
+
+            interface canvas {
+                record point { x: u32, y: u32 }
+                draw: func(at: point);
+            }
+
+            world painter {
+                import canvas;
+            }
+            "#,
+        )
+        .generate()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "In function 'example:shapes/canvas#draw', parameter 'at'"
+    );
+    assert!(format!("{:#}", err).contains("'record' types can't be passed"));
+}
+
+#[test]
+fn test_async_types_are_rejected() {
+    for ty in ["stream<u8>", "future<string>", "error-context"] {
+        let wit = format!(
+            "package example:pipes;\n\
+             interface pipe {{ open: func(source: {}); }}\n\
+             world plumber {{ import pipe; }}",
+            ty
+        );
+        let err = Opts::new().inline(wit).generate().unwrap_err();
+
+        assert!(
+            format!("{:#}", err).ends_with(&format!(
+                "Unsupported type: '{}' is a component model async type, which \
+                 wasm_component_layer can't pass across the component boundary",
+                ty
+            )),
+            "unexpected error: {:#}",
+            err
+        );
+    }
+}
+
+#[test]
+fn test_every_file_is_tracked() {
+    let tests_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests");
+    // prettyplease may break long macro calls over several lines
+    let compact = |source: String| source.split_whitespace().collect::<String>();
+
+    let source = compact(opts().generate_string().unwrap());
+    for file in ["wit-deps/world.wit", "wit-deps/deps/logging/log.wit"] {
+        let include = format!("include_str!(\"{}/{}\")", tests_dir, file);
+        assert!(source.contains(&include), "{} is not tracked", file);
+    }
+
+    let source = compact(
+        Opts::new()
+            .world("calculator")
+            .base_dir(env!("CARGO_MANIFEST_DIR"))
+            .path("tests/wit-split/main")
+            .path("tests/wit-split/logging.wasm")
+            .generate_string()
+            .unwrap(),
+    );
+    let include = format!("include_bytes!(\"{}/wit-split/logging.wasm\")", tests_dir);
+    assert!(source.contains(&include), "logging.wasm is not tracked");
+}
+
+#[test]
+fn test_unknown_world_lists_the_available_ones() {
+    let err = opts().world("calculator2").generate().unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "World 'calculator2' not found in package 'example:calculator@0.1.0', \
+         available worlds: 'calculator'\n\nhelp: did you mean 'calculator'?"
+    );
+    let located = err.downcast_ref::<LocatedError>().unwrap();
+    assert!(matches!(located.location, Location::Key("world")));
+}
+
+#[test]
+fn test_inline_syntax_error_location() {
+    let err = Opts::new()
+        .inline("package example:broken;\n\ninterface api {\n    f: func(x: u32) -> ;\n}\n")
+        .generate()
+        .unwrap_err();
+
+    let located = err.downcast_ref::<LocatedError>().unwrap();
+    assert!(
+        matches!(located.location, Location::Inline { line: 4, column: 24 }),
+        "unexpected location: {:?}",
+        located.location
+    );
+    assert!(err.to_string().contains("--> inline.wit:4:24"));
+}
+
+#[test]
+fn test_inline_error_after_multi_byte_characters() {
+    use wit_derive_codegen::error::inline_source_range;
+
+    let wit = "package example:broken;\n\n// café\ninterface api {\n    /* é */ f: func(x: u32) -> ;\n}\n";
+    let err = Opts::new().inline(wit).generate().unwrap_err();
+
+    // `é` takes two bytes of the column
+    let located = err.downcast_ref::<LocatedError>().unwrap();
+    let Location::Inline { line, column } = located.location else {
+        panic!("unexpected location: {:?}", located.location);
+    };
+    assert_eq!((line, column), (5, 33));
+
+    // Within a raw string, and within a string with escapes
+    let range = inline_source_range(wit, true, line, column).unwrap();
+    assert_eq!(&wit[range], ";");
+    let escaped = r"package example:broken;\n\n// caf\u{e9}\n\
+        interface api {\n    /* \u{e9} */ f: func(x: u32) -> \x3b\n}\n";
+    let range = inline_source_range(escaped, false, line, column).unwrap();
+    assert_eq!(&escaped[range], "\\x3b");
+}
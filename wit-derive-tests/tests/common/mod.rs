@@ -0,0 +1,49 @@
+// Helpers shared by the integration tests, not every test uses all of them
+#![allow(dead_code)]
+
+use anyhow::Result;
+
+/// Builds a component of `world` from a core module written in WAT.
+///
+/// This is the equivalent of `wasm-tools component embed` followed by
+/// `wasm-tools component new`.
+pub fn build_component(wit: &str, world: &str, wat: &str) -> Result<Vec<u8>> {
+    let mut resolve = wit_parser::Resolve::default();
+    let pkg_id = resolve.push_str(format!("{}.wit", world), wit)?;
+    let world_id = resolve.select_world(pkg_id, Some(world))?;
+
+    let mut core_wasm = wat::parse_str(wat)?;
+    wit_component::embed_component_metadata(
+        &mut core_wasm,
+        &resolve,
+        world_id,
+        wit_component::StringEncoding::UTF8,
+    )?;
+
+    let component_bytes = wit_component::ComponentEncoder::default()
+        .module(&core_wasm)?
+        .validate(true)
+        .encode()?;
+
+    Ok(component_bytes)
+}
+
+/// The engine the tests run components on.
+pub type Engine = wasm_component_layer::Engine<wasmi_runtime_layer::Engine>;
+
+/// Compiles `wasm_blob` on a fresh wasmi engine.
+pub fn load(wasm_blob: &[u8]) -> Result<(Engine, wasm_component_layer::Component)> {
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let component = wasm_component_layer::Component::new(&engine, wasm_blob)?;
+    Ok((engine, component))
+}
+
+/// Compiles `wasm_blob` on a fresh wasmi engine and runs `f` with the engine and the component.
+pub fn run_with<R>(
+    wasm_blob: &[u8],
+    f: impl FnOnce(&Engine, &wasm_component_layer::Component) -> Result<R>,
+) -> Result<R> {
+    let (engine, component) = load(wasm_blob)?;
+    f(&engine, &component)
+}
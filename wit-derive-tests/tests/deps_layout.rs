@@ -0,0 +1,134 @@
+// Resolving a WIT directory together with its `deps/` folder
+
+wit_derive::generate!({
+    world: "calculator",
+    path: "tests/wit-deps",
+    resolve: true,
+});
+
+// The main package and its dependency in separate directories
+mod directory_dependency {
+    wit_derive::generate!({
+        world: "calculator",
+        path: ["tests/wit-split/main", "tests/wit-deps/deps/logging"],
+    });
+}
+
+// Single file dependencies, keyed by the given path
+mod file_dependency {
+    wit_derive::generate!({
+        world: "calculator",
+        path: ["tests/wit-split/main", "tests/wit-deps/deps/logging/log.wit"],
+    });
+}
+
+// The dependency as an encoded WIT package. After changing log.wit, regenerate it with
+// `wasm-tools component wit tests/wit-deps/deps/logging/log.wit --wasm -o tests/wit-split/logging.wasm`
+mod encoded_dependency {
+    wit_derive::generate!({
+        world: "calculator",
+        path: ["tests/wit-split/main", "tests/wit-split/logging.wasm"],
+        resolve: true,
+    });
+}
+
+pub struct MyConsoleImpl;
+
+impl crate::calculator::console for MyConsoleImpl {
+    fn print(&mut self, message: String) {
+        println!("[WASM]: {}", message);
+    }
+}
+
+pub struct MyLogImpl;
+
+impl crate::calculator::log for MyLogImpl {
+    fn info(&mut self, message: String) {
+        println!("[LOG]: {}", message);
+    }
+}
+
+#[test]
+fn test_imports_from_dependency_package() {
+    // The `log` interface lives in `deps/logging` but is still part of the imports
+    let _imports = crate::calculator::Imports {
+        console: Box::new(MyConsoleImpl),
+        log: Box::new(MyLogImpl),
+    };
+}
+
+#[test]
+fn test_imports_from_dependency_paths() {
+    // `log` comes from the dependency path in both cases
+    let _imports = directory_dependency::calculator::Imports::builder()
+        .console_print(|_| ())
+        .log_info(|_| ())
+        .build();
+    let _imports = encoded_dependency::calculator::Imports::builder()
+        .console_print(|_| ())
+        .log_info(|_| ())
+        .build();
+
+    let keys: Vec<_> = directory_dependency::calculator::wit::files::FILES
+        .iter()
+        .map(|(path, _)| *path)
+        .collect();
+    assert_eq!(keys, vec!["tests/wit-deps/deps/logging/log.wit", "world.wit"]);
+}
+
+#[test]
+fn test_encoded_dependency_is_part_of_the_package() {
+    let (resolve, world_id) = encoded_dependency::calculator::wit::resolve().unwrap();
+
+    assert_eq!(resolve.worlds[world_id].imports.len(), 2);
+    assert!(
+        resolve
+            .packages
+            .iter()
+            .any(|(_, package)| package.name.to_string() == "example:logging@0.2.0")
+    );
+}
+
+#[test]
+fn test_files_keyed_by_relative_path() {
+    let files = crate::calculator::wit::files::FILES;
+    assert_eq!(files.len(), 2);
+
+    let log = crate::calculator::wit::files::lookup("deps/logging/log.wit").unwrap();
+    assert!(log.contains("package example:logging@0.2.0;"));
+    assert!(crate::calculator::wit::files::lookup("world.wit").is_some());
+    assert!(crate::calculator::wit::files::lookup("log.wit").is_none());
+}
+
+#[test]
+fn test_encoded_package_contains_dependencies() {
+    let (resolve, world_id) = crate::calculator::wit::resolve().unwrap();
+
+    let world = &resolve.worlds[world_id];
+    assert_eq!(world.name, crate::calculator::wit::WORLD);
+    assert_eq!(world.imports.len(), 2);
+    assert_eq!(world.exports.len(), 1);
+
+    let packages: Vec<_> = resolve
+        .packages
+        .iter()
+        .map(|(_, package)| package.name.to_string())
+        .collect();
+    assert!(packages.contains(&"example:logging@0.2.0".to_string()));
+}
+
+#[test]
+fn test_single_file_dependencies_keyed_by_their_path() {
+    use file_dependency::calculator::wit::files;
+
+    let keys: Vec<_> = files::FILES.iter().map(|(path, _)| *path).collect();
+    assert_eq!(keys, vec!["tests/wit-deps/deps/logging/log.wit", "world.wit"]);
+    assert!(files::lookup("tests/wit-deps/deps/logging/log.wit/log.wit").is_none());
+
+    // Encoded packages are tracked as build inputs, but only WIT sources are listed
+    let keys: Vec<_> = encoded_dependency::calculator::wit::files::FILES
+        .iter()
+        .map(|(path, _)| *path)
+        .collect();
+    assert_eq!(keys, vec!["world.wit"]);
+}
@@ -0,0 +1,114 @@
+// Worlds pulling in other worlds with `include`, and interfaces declared inline in a world
+
+use anyhow::Result;
+use wit_component::ComponentEncoder;
+
+wit_derive::generate!({
+    world: "shapes",
+    inline: r#"
+        package example:shapes@0.1.0;
+
+        world base {
+            import host: interface {
+                next: func(seed: u32) -> u32;
+            }
+        }
+
+        world shapes {
+            include base;
+
+            export tools: interface {
+                twice: func(x: u32) -> u32;
+            }
+        }
+    "#,
+});
+
+struct Host;
+
+impl shapes::host for Host {
+    fn next(&mut self, seed: u32) -> u32 {
+        seed + 1
+    }
+}
+
+/// `twice(x)` returns `next(x) * 2`. Inline interfaces are mangled by their plain name.
+pub fn create_shapes_component() -> Result<Vec<u8>> {
+    let mut resolve = wit_parser::Resolve::default();
+    let pkg_id = resolve.push_str("shapes.wit", shapes::wit::INLINE)?;
+    let world_id = resolve.select_world(pkg_id, Some("shapes"))?;
+
+    let wat_source = r#"
+(module
+  (type (;0;) (func (param i32) (result i32)))
+  (type (;1;) (func))
+  (import "cm32p2|host" "next" (func (;0;) (type 0)))
+  (memory (;0;) 0)
+  (export "cm32p2|tools|twice" (func 1))
+  (export "cm32p2_memory" (memory 0))
+  (export "cm32p2_initialize" (func 2))
+  (func (;1;) (type 0) (param i32) (result i32)
+    local.get 0
+    call 0
+    i32.const 2
+    i32.mul
+  )
+  (func (;2;) (type 1))
+)
+"#;
+
+    let mut core_wasm = wat::parse_str(wat_source)?;
+    wit_component::embed_component_metadata(
+        &mut core_wasm,
+        &resolve,
+        world_id,
+        wit_component::StringEncoding::UTF8,
+    )?;
+
+    let component_bytes = ComponentEncoder::default()
+        .module(&core_wasm)?
+        .validate(true)
+        .encode()?;
+
+    Ok(component_bytes)
+}
+
+// Test
+// ----------------------------------------------------------
+
+#[test]
+fn test_included_world_is_flattened() {
+    let imports = shapes::world_imports();
+    assert_eq!(imports.len(), 1);
+    assert_eq!(imports[0].name, "host");
+    assert!(imports[0].id.is_none());
+    assert!(imports[0].function("next").is_some());
+
+    let _imports = shapes::Imports {
+        host: Box::new(Host),
+    };
+}
+
+#[test]
+fn test_inline_interfaces_link_on_the_root() -> Result<()> {
+    let wasm_blob = create_shapes_component()?;
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob)?;
+
+    shapes::check_compatibility(&component)?;
+
+    let imports = shapes::Imports {
+        host: Box::new(Host),
+    };
+    let mut instance = shapes::instantiate(store, &component, imports)?;
+    assert_eq!(instance.tools.twice(4), 10);
+    assert_eq!(
+        instance.call_dynamic("tools", "twice", &[wasm_component_layer::Value::U32(1)])?,
+        vec![wasm_component_layer::Value::U32(4)]
+    );
+
+    Ok(())
+}
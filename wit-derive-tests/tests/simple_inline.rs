@@ -1,7 +1,6 @@
 // Example demonstrating the inline WIT functionality
 
 use anyhow::Result;
-use wit_component::ComponentEncoder;
 
 wit_derive::generate!({
     world: "calculator",
@@ -24,6 +23,22 @@ wit_derive::generate!({
     "#,
 });
 
+// The same package, but the world expects an additional export
+mod extended {
+    wit_derive::generate!({
+        world: "calculator",
+        path: "tests/wit-calculator/extended",
+    });
+}
+
+// A newer patch release of the package, which is semver compatible with the component
+mod newer {
+    wit_derive::generate!({
+        world: "calculator",
+        path: "tests/wit-calculator/newer",
+    });
+}
+
 // Client
 // ----------------------------------------------------------
 
@@ -102,6 +117,12 @@ impl crate::calculator::console for MyConsoleImpl {
     }
 }
 
+impl crate::newer::calculator::console for MyConsoleImpl {
+    fn print(&mut self, message: String) {
+        println!("[WASM]: {}", message);
+    }
+}
+
 // Test
 // ----------------------------------------------------------
 
@@ -131,3 +152,165 @@ fn test_inline_wit() -> Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn test_check_compatibility() -> Result<()> {
+    let wasm_blob = create_inline_wat_component().unwrap();
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob).unwrap();
+
+    assert!(crate::calculator::check_compatibility(&component).is_ok());
+
+    let mismatch = crate::extended::calculator::check_compatibility(&component).unwrap_err();
+    assert_eq!(
+        mismatch.mismatches,
+        vec![crate::extended::calculator::Mismatch::MissingExport {
+            interface: "example:calculator/math@0.1.0".to_string(),
+            function: "divide".to_string(),
+            expected: "divide: func(a: s32, b: s32) -> s32;".to_string(),
+        }]
+    );
+    println!("{}", mismatch);
+    Ok(())
+}
+
+#[test]
+fn test_semver_compatible_versions_link() -> Result<()> {
+    let wasm_blob = create_inline_wat_component().unwrap();
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob).unwrap();
+
+    // The component was built against 0.1.0, the bindings against 0.1.5
+    assert!(crate::newer::calculator::check_compatibility(&component).is_ok());
+
+    let imports = crate::newer::calculator::Imports {
+        console: Box::new(MyConsoleImpl),
+    };
+    let mut instance = crate::newer::calculator::instantiate(store, &component, imports)?;
+    assert_eq!(instance.math.multiply(6, 7), 42);
+    Ok(())
+}
+
+#[test]
+fn test_pre_instantiates_repeatedly() -> Result<()> {
+    let wasm_blob = create_inline_wat_component().unwrap();
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob).unwrap();
+
+    let pre = crate::calculator::Pre::new(&component)?;
+    for i in 0..3 {
+        let store = wasm_component_layer::Store::new(&engine, ());
+        let imports = crate::calculator::Imports {
+            console: Box::new(MyConsoleImpl),
+        };
+        let mut instance = pre.instantiate(store, imports)?;
+        assert_eq!(instance.math.add(i, 1), i + 1);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pre_limits_live_instances() -> Result<()> {
+    use crate::calculator::{LimitExceeded, Limits};
+
+    let wasm_blob = create_inline_wat_component().unwrap();
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob).unwrap();
+
+    let pre = crate::calculator::Pre::new(&component)?.with_limits(Limits {
+        max_instances: Some(1),
+    });
+    let instantiate = || {
+        let store = wasm_component_layer::Store::new(&engine, ());
+        let imports = crate::calculator::Imports {
+            console: Box::new(MyConsoleImpl),
+        };
+        pre.instantiate(store, imports)
+    };
+
+    let first = instantiate()?;
+    let error = instantiate().err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<LimitExceeded>(),
+        Some(&LimitExceeded::Instances { limit: 1 })
+    );
+
+    // Handing back the store frees the slot
+    first.into_store()?;
+    instantiate()?;
+    Ok(())
+}
+
+#[test]
+fn test_exports_give_back_the_store() -> Result<()> {
+    let wasm_blob = create_inline_wat_component().unwrap();
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let store = wasm_component_layer::Store::new(&engine, 1u32);
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob).unwrap();
+
+    let imports = crate::calculator::Imports {
+        console: Box::new(MyConsoleImpl),
+    };
+    let mut instance = crate::calculator::instantiate(store, &component, imports)?;
+    assert_eq!(*instance.store().data(), 1);
+
+    *instance.store_mut().data_mut() += 1;
+    assert_eq!(instance.math.add(2, 3), 5);
+    assert!(instance.instance().exports().instances().count() > 0);
+
+    let store = instance.into_store()?;
+    assert_eq!(store.into_data(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_call_dynamic() -> Result<()> {
+    use wasm_component_layer::{Value, ValueType};
+
+    let exports = crate::calculator::world_exports();
+    let add = exports[0].function("add").unwrap();
+    assert_eq!(
+        add.params,
+        vec![("a", ValueType::S32), ("b", ValueType::S32)]
+    );
+    assert_eq!(add.results, vec![ValueType::S32]);
+
+    let wasm_blob = create_inline_wat_component().unwrap();
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob).unwrap();
+
+    let imports = crate::calculator::Imports {
+        console: Box::new(MyConsoleImpl),
+    };
+    let mut instance = crate::calculator::instantiate(store, &component, imports)?;
+
+    let results = instance.call_dynamic("math", "add", &[Value::S32(2), Value::S32(3)])?;
+    assert_eq!(results, vec![Value::S32(5)]);
+    let results = instance.call_dynamic(
+        "example:calculator/math@0.1.0",
+        "multiply",
+        &[Value::S32(6), Value::S32(7)],
+    )?;
+    assert_eq!(results, vec![Value::S32(42)]);
+
+    let error = instance
+        .call_dynamic("math", "add", &[Value::S32(2), Value::U8(3)])
+        .unwrap_err();
+    assert_eq!(error.to_string(), "Argument `b` of `add` must be s32, got u8");
+    assert!(instance.call_dynamic("math", "divide", &[]).is_err());
+    Ok(())
+}
@@ -0,0 +1,68 @@
+// Guest components built from the world with `stub_component: true`
+
+use anyhow::Result;
+use wasm_encoder::Instruction;
+
+wit_derive::generate!({
+    world: "calculator",
+    stub_component: true,
+    inline: r#"
+        package example:calculator@0.1.0;
+
+        interface math {
+            add: func(a: s32, b: s32) -> s32;
+            multiply: func(a: s32, b: s32) -> s32;
+        }
+
+        interface console {
+            print: func(line: string);
+        }
+
+        world calculator {
+            import console;
+            export math;
+        }
+    "#,
+});
+
+pub struct MyConsoleImpl;
+
+impl crate::calculator::console for MyConsoleImpl {
+    fn print(&mut self, line: String) {
+        println!("[WASM]: {}", line);
+    }
+}
+
+#[test]
+fn test_stub_component() -> Result<()> {
+    let wasm_blob = crate::calculator::stub_component(&[(
+        "math#add",
+        &[
+            Instruction::LocalGet(0),
+            Instruction::LocalGet(1),
+            Instruction::I32Add,
+        ],
+    )])?;
+
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob)?;
+    assert!(crate::calculator::check_compatibility(&component).is_ok());
+
+    let imports = crate::calculator::Imports {
+        console: Box::new(MyConsoleImpl),
+    };
+    let mut instance = crate::calculator::instantiate(store, &component, imports)?;
+
+    assert_eq!(instance.math.add(7, 8), 15);
+    // Exports without a body trap
+    let args = [
+        wasm_component_layer::Value::S32(6),
+        wasm_component_layer::Value::S32(7),
+    ];
+    assert!(instance.call_dynamic("math", "multiply", &args).is_err());
+
+    assert!(crate::calculator::stub_component(&[("math#divide", &[Instruction::Unreachable])]).is_err());
+    Ok(())
+}
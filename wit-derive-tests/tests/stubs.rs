@@ -0,0 +1,489 @@
+// Recording stubs generated with `stubs: true`
+
+use anyhow::Result;
+
+mod common;
+
+wit_derive::generate!({
+    world: "stubbed",
+    stubs: true,
+    path: "tests/wit-stubs",
+});
+
+// The same world, but the host may leave interfaces out
+mod partial {
+    wit_derive::generate!({
+        world: "stubbed",
+        optional_imports: true,
+        path: "tests/wit-stubs",
+    });
+}
+
+// The same world for hosts that aren't `Send`
+mod single {
+    wit_derive::generate!({
+        world: "stubbed",
+        threading: "single",
+        path: "tests/wit-stubs",
+    });
+}
+
+// The same world with imports and exports returning futures
+mod asynchronous {
+    wit_derive::generate!({
+        world: "stubbed",
+        async: true,
+        path: "tests/wit-stubs",
+    });
+}
+
+// A world that provides `host` to the guest above
+mod provider {
+    wit_derive::generate!({
+        world: "provider",
+        path: "tests/wit-stubs",
+    });
+}
+
+use crate::stubs::{HostCall, RecordingHost};
+
+// Client
+// ----------------------------------------------------------
+
+const STUBS_WIT: &str = include_str!("wit-stubs/world.wit");
+
+/// A guest whose `run(x)` logs once and returns `next(x) + next(x + 1)`.
+pub fn create_stubbed_component() -> Result<Vec<u8>> {
+    let wat_source = r#"
+(module
+  (type (;0;) (func (param i32) (result i32)))
+  (type (;1;) (func (param i32)))
+  (type (;2;) (func (param i32 i32 i32 i32) (result i32)))
+  (type (;3;) (func))
+  (import "cm32p2|example:stubs/host@0.1" "next" (func (;0;) (type 0)))
+  (import "cm32p2|example:stubs/host@0.1" "log" (func (;1;) (type 1)))
+  (memory (;0;) 0)
+  (export "cm32p2|example:stubs/guest@0.1|run" (func 2))
+  (export "cm32p2|example:stubs/guest@0.1|run_post" (func 3))
+  (export "cm32p2_memory" (memory 0))
+  (export "cm32p2_realloc" (func 4))
+  (export "cm32p2_initialize" (func 5))
+  (func (;2;) (type 0) (param i32) (result i32)
+    i32.const 1
+    call 1
+    local.get 0
+    call 0
+    local.get 0
+    i32.const 1
+    i32.add
+    call 0
+    i32.add
+  )
+  (func (;3;) (type 1) (param i32))
+  (func (;4;) (type 2) (param i32 i32 i32 i32) (result i32)
+    unreachable
+  )
+  (func (;5;) (type 3))
+)
+"#;
+
+    common::build_component(STUBS_WIT, "stubbed", wat_source)
+}
+
+/// A provider whose `next(seed)` returns `seed * 3`.
+pub fn create_provider_component() -> Result<Vec<u8>> {
+    let wat_source = r#"
+(module
+  (type (;0;) (func (param i32) (result i32)))
+  (type (;1;) (func (param i32)))
+  (type (;2;) (func (param i32 i32 i32 i32) (result i32)))
+  (type (;3;) (func))
+  (memory (;0;) 0)
+  (export "cm32p2|example:stubs/host@0.1|next" (func 0))
+  (export "cm32p2|example:stubs/host@0.1|next_post" (func 1))
+  (export "cm32p2|example:stubs/host@0.1|log" (func 1))
+  (export "cm32p2_memory" (memory 0))
+  (export "cm32p2_realloc" (func 2))
+  (export "cm32p2_initialize" (func 3))
+  (func (;0;) (type 0) (param i32) (result i32)
+    local.get 0
+    i32.const 3
+    i32.mul
+  )
+  (func (;1;) (type 1) (param i32))
+  (func (;2;) (type 2) (param i32 i32 i32 i32) (result i32)
+    unreachable
+  )
+  (func (;3;) (type 3))
+)
+"#;
+
+    common::build_component(STUBS_WIT, "provider", wat_source)
+}
+
+fn run_guest(host: &RecordingHost, x: u32) -> Result<u32> {
+    common::run_with(&create_stubbed_component()?, |engine, component| {
+        let store = wasm_component_layer::Store::new(engine, ());
+        let imports = stubs::Imports {
+            host: Box::new(host.clone()),
+        };
+        let mut instance = stubs::instantiate(store, component, imports)?;
+        Ok(instance.guest.run(x))
+    })
+}
+
+// Test
+// ----------------------------------------------------------
+
+#[test]
+fn test_recording_stub_logs_calls_in_order() -> Result<()> {
+    let host = RecordingHost::default().with_next(|seed| seed * 10);
+
+    assert_eq!(run_guest(&host, 4)?, 90);
+    assert_eq!(
+        host.calls(),
+        vec![
+            HostCall::Log { level: 1 },
+            HostCall::Next { seed: 4 },
+            HostCall::Next { seed: 5 },
+        ]
+    );
+
+    host.clear();
+    assert!(host.calls().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_recording_stub_answers() -> Result<()> {
+    // Unconfigured functions answer with `Default::default()`
+    assert_eq!(run_guest(&RecordingHost::default(), 4)?, 0);
+
+    let host = RecordingHost::default().returning_next(7);
+    assert_eq!(run_guest(&host, 4)?, 14);
+    Ok(())
+}
+
+#[test]
+fn test_recording_stub_handler_sees_the_calls() -> Result<()> {
+    // The handler runs after the state is released, so it may use the stub itself
+    let host = RecordingHost::default();
+    let recorded = host.clone();
+    let host = host.with_next(move |_| recorded.calls().len() as u32);
+
+    // `log`, then `next` answers 2 and 3
+    assert_eq!(run_guest(&host, 4)?, 5);
+    Ok(())
+}
+
+#[test]
+fn test_imports_from_closures() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+    use wasm_component_layer::Value;
+
+    let (engine, component) = common::load(&create_stubbed_component()?)?;
+    let pre = stubs::Pre::new(&component)?;
+
+    let levels = Arc::new(Mutex::new(Vec::new()));
+    let logged = levels.clone();
+    let imports = stubs::Imports::builder()
+        .host_next(|seed| seed + 1)
+        .host_log(move |level| logged.lock().unwrap().push(level))
+        .build();
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let mut instance = pre.instantiate(store, imports)?;
+    assert_eq!(instance.guest.run(4), 11);
+    assert_eq!(*levels.lock().unwrap(), vec![1]);
+
+    // `log` is left unset, so the guest traps on its first call
+    let imports = stubs::Imports::builder().host_next(|seed| seed).build();
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let mut instance = pre.instantiate(store, imports)?;
+    let error = instance
+        .call_dynamic("guest", "run", &[Value::U32(4)])
+        .unwrap_err();
+    assert!(
+        error
+            .chain()
+            .any(|cause| cause.to_string().contains("unimplemented import `example:stubs/host#log`")),
+        "unexpected error: {:?}",
+        error
+    );
+
+    // The trap comes from an error, not a panic, so it works without unwinding as well
+    let mut host = stubs::HostClosures::default();
+    let error = stubs::host::try_log(&mut host, 1).unwrap_err();
+    assert_eq!(error.to_string(), "unimplemented import `example:stubs/host#log`");
+    Ok(())
+}
+
+#[test]
+fn test_missing_imports_trap() -> Result<()> {
+    use wasm_component_layer::Value;
+
+    let imports = partial::stubs::Imports::default();
+    assert_eq!(imports.missing_imports(), vec!["example:stubs/host"]);
+
+    let (engine, component) = common::load(&create_stubbed_component()?)?;
+    let store = wasm_component_layer::Store::new(&engine, ());
+
+    // Instantiating works, the missing interface only fails once it is called
+    let mut instance = partial::stubs::instantiate(store, &component, imports)?;
+    let error = instance
+        .call_dynamic("guest", "run", &[Value::U32(4)])
+        .unwrap_err();
+    assert!(
+        error.chain().any(|cause| cause
+            .to_string()
+            .contains("import `example:stubs/host#log` not provided by host")),
+        "unexpected error: {:?}",
+        error
+    );
+
+    let imports = partial::stubs::Imports::builder()
+        .host_next(|seed| seed * 2)
+        .host_log(|_| ())
+        .build();
+    assert!(imports.missing_imports().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_builder_leaves_out_interfaces_without_closures() {
+    let imports = partial::stubs::Imports::builder().build();
+    assert_eq!(imports.missing_imports(), vec!["example:stubs/host"]);
+
+    // A single closure is enough to provide the interface
+    let imports = partial::stubs::Imports::builder()
+        .host_next(|seed| seed)
+        .build();
+    assert!(imports.missing_imports().is_empty());
+}
+
+#[test]
+fn test_store_survives_a_trapping_export() -> Result<()> {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let (engine, component) = common::load(&create_stubbed_component()?)?;
+    let store = wasm_component_layer::Store::new(&engine, 7u32);
+
+    // The typed export panics on the trap while holding the store, poisoning its mutex
+    let mut instance =
+        partial::stubs::instantiate(store, &component, partial::stubs::Imports::default())?;
+    assert!(catch_unwind(AssertUnwindSafe(|| instance.guest.run(4))).is_err());
+
+    assert_eq!(*instance.store().data(), 7);
+    let store = instance.into_store()?;
+    assert_eq!(store.into_data(), 7);
+    Ok(())
+}
+
+/// Only lets the guest call `next` when the store data allows it, and clamps its results.
+struct Policy {
+    seen: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+impl stubs::Interceptor<bool> for Policy {
+    fn before_call(
+        &mut self,
+        allowed: &bool,
+        interface: &str,
+        function: &str,
+        _args: &[wasm_component_layer::Value],
+    ) -> Result<()> {
+        self.seen
+            .lock()
+            .unwrap()
+            .push(format!("{}#{}", interface, function));
+        if function == "next" && !*allowed {
+            anyhow::bail!("permission denied");
+        }
+        Ok(())
+    }
+
+    fn after_call(
+        &mut self,
+        _allowed: &bool,
+        _interface: &str,
+        function: &str,
+        _args: &[wasm_component_layer::Value],
+        results: &mut [wasm_component_layer::Value],
+    ) -> Result<()> {
+        if function == "next" {
+            results[0] = wasm_component_layer::Value::U32(1);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_interceptor_wraps_imports() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+    use wasm_component_layer::Value;
+
+    let (engine, component) = common::load(&create_stubbed_component()?)?;
+    let pre = stubs::Pre::new(&component)?;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let host = RecordingHost::default().with_next(|seed| seed * 10);
+    let imports = stubs::Imports {
+        host: Box::new(host.clone()),
+    }
+    .with_interceptor(Policy { seen: seen.clone() });
+    let store = wasm_component_layer::Store::new(&engine, true);
+    let mut instance = pre.instantiate(store, imports)?;
+    assert_eq!(instance.guest.run(4), 2);
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            "example:stubs/host#log",
+            "example:stubs/host#next",
+            "example:stubs/host#next",
+        ]
+    );
+
+    // Denied calls never reach the host
+    host.clear();
+    let imports = stubs::Imports {
+        host: Box::new(host.clone()),
+    }
+    .with_interceptor(Policy { seen });
+    let store = wasm_component_layer::Store::new(&engine, false);
+    let mut instance = pre.instantiate(store, imports)?;
+    let error = instance
+        .call_dynamic("guest", "run", &[Value::U32(4)])
+        .unwrap_err();
+    assert!(
+        error
+            .chain()
+            .any(|cause| cause.to_string().contains("permission denied")),
+        "unexpected error: {:?}",
+        error
+    );
+    assert_eq!(host.calls(), vec![HostCall::Log { level: 1 }]);
+    Ok(())
+}
+
+/// Keeps its state in an `Rc`, which the default bindings would reject.
+struct RcHost {
+    levels: std::rc::Rc<std::cell::RefCell<Vec<u8>>>,
+}
+
+impl single::stubs::host for RcHost {
+    fn next(&mut self, seed: u32) -> u32 {
+        seed + 1
+    }
+
+    fn log(&mut self, level: u8) {
+        self.levels.borrow_mut().push(level);
+    }
+}
+
+#[test]
+fn test_single_threaded_bindings() -> Result<()> {
+    common::run_with(&create_stubbed_component()?, |engine, component| {
+        let store = wasm_component_layer::Store::new(engine, ());
+        let levels = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let imports = single::stubs::Imports {
+            host: Box::new(RcHost {
+                levels: levels.clone(),
+            }),
+        };
+        let mut instance = single::stubs::instantiate(store, component, imports)?;
+        assert_eq!(instance.guest.run(4), 11);
+        assert_eq!(*levels.borrow(), vec![1]);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_forward_exports_to_imports() -> Result<()> {
+    let (engine, provider_component) = common::load(&create_provider_component()?)?;
+    let provider = provider::stubs::instantiate(
+        wasm_component_layer::Store::new(&engine, ()),
+        &provider_component,
+        provider::stubs::Imports {},
+    )?;
+
+    // The guest's `host` imports are served by the provider's `host` exports
+    let guest_component =
+        wasm_component_layer::Component::new(&engine, &create_stubbed_component()?)?;
+    let imports = stubs::Imports {
+        host: Box::new(stubs::HostForwarder::new(provider.forwarder())),
+    };
+    let mut guest = stubs::instantiate(
+        wasm_component_layer::Store::new(&engine, ()),
+        &guest_component,
+        imports,
+    )?;
+    assert_eq!(guest.guest.run(4), 27);
+    Ok(())
+}
+
+#[test]
+fn test_failed_forwarding_is_an_error() {
+    use wasm_component_layer::Value;
+
+    let mut forwarder = stubs::HostForwarder::new(
+        |_: &str, _: &str, _: &[Value]| -> Result<Vec<Value>> { anyhow::bail!("provider is gone") },
+    );
+    let error = stubs::host::try_next(&mut forwarder, 4).unwrap_err();
+    assert_eq!(
+        format!("{:#}", error),
+        "forwarding `host#next` failed: provider is gone"
+    );
+
+    // A result of the wrong type is an error as well
+    let mut forwarder = stubs::HostForwarder::new(
+        |_: &str, _: &str, _: &[Value]| -> Result<Vec<Value>> { Ok(vec![Value::String("4".into())]) },
+    );
+    assert!(stubs::host::try_next(&mut forwarder, 4).is_err());
+}
+
+/// Answers `next` only after yielding to the runtime.
+struct YieldingHost;
+
+impl asynchronous::stubs::host for YieldingHost {
+    fn next(&mut self, seed: u32) -> asynchronous::stubs::BoxFuture<'_, u32> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            seed * 10
+        })
+    }
+
+    fn log(&mut self, _level: u8) -> asynchronous::stubs::BoxFuture<'_, ()> {
+        Box::pin(async {})
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_async_imports_called_from_exports() -> Result<()> {
+    let (engine, component) = common::load(&create_stubbed_component()?)?;
+    let store = wasm_component_layer::Store::new(&engine, ());
+
+    let imports = asynchronous::stubs::Imports {
+        host: Box::new(YieldingHost),
+    };
+    let mut instance = asynchronous::stubs::instantiate(store, &component, imports).await?;
+    assert_eq!(instance.guest.run(4).await, 90);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_async_call_dynamic() -> Result<()> {
+    use wasm_component_layer::Value;
+
+    let (engine, component) = common::load(&create_stubbed_component()?)?;
+    let store = wasm_component_layer::Store::new(&engine, ());
+
+    let imports = asynchronous::stubs::Imports {
+        host: Box::new(YieldingHost),
+    };
+    let mut instance = asynchronous::stubs::instantiate(store, &component, imports).await?;
+    // Runs on the blocking pool, where the import futures can be driven
+    let results = instance
+        .call_dynamic("guest", "run", &[Value::U32(4)])
+        .await?;
+    assert_eq!(results, vec![Value::U32(90)]);
+    Ok(())
+}
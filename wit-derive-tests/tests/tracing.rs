@@ -0,0 +1,128 @@
+// Spans emitted by bindings generated with `tracing: true`
+
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+mod common;
+
+wit_derive::generate!({
+    world: "traced",
+    tracing: true,
+    inline: r#"
+        package example:traced@0.1.0;
+
+        interface host {
+            next: func(seed: u32) -> u32;
+        }
+
+        interface guest {
+            run: func(x: u32) -> u32;
+        }
+
+        world traced {
+            import host;
+            export guest;
+        }
+    "#,
+});
+
+/// A guest whose `run(x)` returns `next(x)`.
+pub fn create_traced_component() -> Result<Vec<u8>> {
+    let wat_source = r#"
+(module
+  (type (;0;) (func (param i32) (result i32)))
+  (type (;1;) (func))
+  (import "cm32p2|example:traced/host@0.1" "next" (func (;0;) (type 0)))
+  (memory (;0;) 0)
+  (export "cm32p2|example:traced/guest@0.1|run" (func 1))
+  (export "cm32p2_memory" (memory 0))
+  (export "cm32p2_initialize" (func 2))
+  (func (;1;) (type 0) (param i32) (result i32)
+    local.get 0
+    call 0
+  )
+  (func (;2;) (type 1))
+)
+"#;
+
+    common::build_component(traced::wit::INLINE, "traced", wat_source)
+}
+
+pub struct MyHostImpl;
+
+impl crate::traced::host for MyHostImpl {
+    fn next(&mut self, seed: u32) -> u32 {
+        seed + 1
+    }
+}
+
+/// Records every new span as its name followed by its `interface` and `function` fields,
+/// and the `result` later recorded on it.
+#[derive(Clone, Default)]
+struct SpanRecorder(Arc<Mutex<Vec<String>>>);
+
+struct FieldWriter<'a>(&'a mut String);
+
+impl Visit for FieldWriter<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if matches!(field.name(), "interface" | "function") {
+            self.0.push_str(&format!(" {}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "result" {
+            self.0.push_str(&format!(" result={:?}", value));
+        }
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for SpanRecorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        let mut span = attrs.metadata().name().to_string();
+        attrs.record(&mut FieldWriter(&mut span));
+        self.0.lock().unwrap().push(span);
+    }
+
+    fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut recorded = String::new();
+        values.record(&mut FieldWriter(&mut recorded));
+        if !recorded.is_empty() {
+            self.0.lock().unwrap().push(recorded);
+        }
+    }
+}
+
+#[test]
+fn test_calls_run_in_spans() -> Result<()> {
+    let wasm_blob = create_traced_component()?;
+    let wasmi_engine = wasmi_runtime_layer::Engine::default();
+    let engine = wasm_component_layer::Engine::new(wasmi_engine);
+    let store = wasm_component_layer::Store::new(&engine, ());
+    let component = wasm_component_layer::Component::new(&engine, &wasm_blob)?;
+
+    let recorder = SpanRecorder::default();
+    let subscriber = tracing_subscriber::registry().with(recorder.clone());
+    tracing::subscriber::with_default(subscriber, || -> Result<()> {
+        let imports = traced::Imports {
+            host: Box::new(MyHostImpl),
+        };
+        let mut instance = traced::instantiate(store, &component, imports)?;
+        assert_eq!(instance.guest.run(4), 5);
+        Ok(())
+    })?;
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec![
+            "export interface=example:traced/guest function=run",
+            "import interface=example:traced/host function=next",
+            " result=[U32(5)]",
+            " result=5",
+        ]
+    );
+    Ok(())
+}
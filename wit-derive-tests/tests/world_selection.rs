@@ -0,0 +1,55 @@
+// Picking the world without naming it, by its qualified name, and from inline WIT that builds
+// on packages on disk
+
+// `wit/` defines a single world
+mod implicit {
+    wit_derive::generate!({
+        path: "wit",
+    });
+}
+
+mod qualified {
+    wit_derive::generate!({
+        world: "example:calculator/calculator@0.1.0",
+        path: "wit",
+    });
+}
+
+mod combined {
+    wit_derive::generate!({
+        path: "tests/wit-deps",
+        inline: r#"
+            package example:embedded;
+
+            world embedded {
+                import example:logging/log@0.2.0;
+                export example:calculator/math@0.1.0;
+            }
+        "#,
+    });
+}
+
+struct Log;
+
+impl combined::embedded::log for Log {
+    fn info(&mut self, _message: String) {}
+}
+
+#[test]
+fn test_single_world_is_picked() {
+    assert_eq!(implicit::calculator::wit::WORLD, "calculator");
+    assert_eq!(qualified::calculator::wit::WORLD, "calculator");
+}
+
+#[test]
+fn test_inline_world_uses_packages_on_disk() {
+    assert_eq!(combined::embedded::wit::WORLD, "embedded");
+    assert!(combined::embedded::wit::INLINE.contains("world embedded"));
+    assert!(combined::embedded::wit::files::lookup("deps/logging/log.wit").is_some());
+
+    let _imports = combined::embedded::Imports { log: Box::new(Log) };
+
+    let exports = combined::embedded::world_exports();
+    assert_eq!(exports.len(), 1);
+    assert!(exports[0].matches("math"));
+}
@@ -0,0 +1,36 @@
+use proc_macro2::Span;
+use wit_derive_codegen::error::{inline_source_range, render, LocatedError, Location};
+
+use crate::parser::MacroInput;
+
+/// Turns any error raised while generating bindings into a spanned `syn::Error`.
+pub fn to_syn_error(err: anyhow::Error, input: &MacroInput) -> syn::Error {
+    let Some(located) = err.downcast_ref::<LocatedError>() else {
+        return syn::Error::new(Span::call_site(), format!("wit-derive error: {}", render(&err)));
+    };
+
+    let span = match located.location {
+        Location::Key("world") => input.world_span,
+        Location::Key("path") => input.path_span,
+        Location::Key("threading") => input.threading_span,
+        Location::Key(_) => input.inline_span,
+        Location::Inline { line, column } => match &input.inline {
+            Some(lit) => inline_span(lit, line, column).unwrap_or(input.inline_span),
+            None => input.inline_span,
+        },
+    };
+
+    syn::Error::new(span, format!("wit-derive error: {}", render(&err)))
+}
+
+/// Resolves a position inside the string literal to a span covering that single character.
+fn inline_span(lit: &syn::LitStr, line: usize, column: usize) -> Option<Span> {
+    // Raw strings may have any number of `#`s around their quotes
+    let token = lit.token().to_string();
+    let content_start = token.find('"')? + 1;
+    let content_end = token.rfind('"')?;
+    let source = token.get(content_start..content_end)?;
+
+    let range = inline_source_range(source, token.starts_with('r'), line, column)?;
+    lit.token().subspan(content_start + range.start..content_start + range.end)
+}
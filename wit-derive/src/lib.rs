@@ -1,15 +1,12 @@
 use anyhow::{Context, Result};
 use proc_macro::TokenStream;
-use quote::quote;
-use std::path::Path;
 use syn::parse_macro_input;
+use wit_derive_codegen::Opts;
 
-mod codegen;
+mod error;
 mod parser;
 
-use crate::codegen::{CodeGenerator, WitSourceContent};
 use crate::parser::MacroInput;
-use wit_parser::{Resolve, UnresolvedPackageGroup};
 
 /// Generates host bindings for a WIT world.
 ///
@@ -23,6 +20,15 @@ use wit_parser::{Resolve, UnresolvedPackageGroup};
 /// });
 /// ```
 ///
+/// Directories are resolved together with their `deps/` folder. A list of directories, `.wit`
+/// files or `.wasm` encoded packages holds the world in its first entry:
+/// ```rust,ignore
+/// wit_derive::generate!({
+///     world: "calculator",
+///     path: ["../wit", "../vendor/logging.wasm"],
+/// });
+/// ```
+///
 /// Using inline WIT content:
 /// ```rust,ignore
 /// wit_derive::generate!({
@@ -46,120 +52,80 @@ use wit_parser::{Resolve, UnresolvedPackageGroup};
 ///     "#,
 /// });
 /// ```
+///
+/// With both `path` and `inline`, the inline WIT holds the main package and can build on the
+/// packages found on disk:
+/// ```rust,ignore
+/// wit_derive::generate!({
+///     path: "../wit",
+///     inline: r#"
+///         package example:plugin;
+///
+///         world plugin {
+///             export example:calculator/math@0.1.0;
+///         }
+///     "#,
+/// });
+/// ```
+///
+/// The other keys switch on optional parts of the bindings, see the methods of the same name
+/// on [`wit_derive_codegen::Opts`]:
+/// ```rust,ignore
+/// wit_derive::generate!({
+///     world: "calculator",
+///     path: "../wit",
+///     stubs: true,
+///     optional_imports: true,
+///     async: true,
+///     stub_component: true,
+///     resolve: true,
+///     tracing: true,
+/// });
+/// ```
+///
+/// An instance can implement the imports of another world through `forwarder()`:
+/// ```rust,ignore
+/// let provider = provider::instantiate(store, &provider_component, provider::Imports {})?;
+/// let imports = calculator::Imports {
+///     math: Box::new(calculator::MathForwarder::new(provider.forwarder())),
+/// };
+/// ```
+///
+/// The same bindings can be written to a file with `Opts` or the `wit-derive` binary:
+/// ```text
+/// wit-derive generate --world calculator wit/ -o src/bindings.rs
+/// ```
 #[proc_macro]
 pub fn generate(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as MacroInput);
 
-    match generate_bindings(input) {
+    match generate_bindings(&input) {
         Ok(tokens) => tokens,
-        Err(e) => {
-            let error_msg = format!("wit-derive error: {:?}", e);
-            quote! {
-                compile_error!(#error_msg);
-            }
-            .into()
-        }
-    }
-}
-
-fn generate_bindings(input: MacroInput) -> Result<TokenStream> {
-    // Parse the WIT files based on the source type and collect source content
-    let (group, wit_source) = match &input.source {
-        crate::parser::WitSource::Path(path) => {
-            // Resolve the path relative to the crate root
-            let manifest_dir =
-                std::env::var("CARGO_MANIFEST_DIR").context("CARGO_MANIFEST_DIR not set")?;
-            let wit_path = Path::new(&manifest_dir).join(path);
-
-            let group = UnresolvedPackageGroup::parse_dir(&wit_path)
-                .with_context(|| format!("Failed to parse WIT files in {}", wit_path.display()))?;
-
-            // Collect all WIT files and their contents
-            let mut files = Vec::new();
-            collect_wit_files(&wit_path, &mut files)?;
-
-            let wit_source = WitSourceContent::Files(files);
-            (group, wit_source)
-        }
-        crate::parser::WitSource::Inline(content) => {
-            let group = UnresolvedPackageGroup::parse("inline.wit", content)
-                .with_context(|| "Failed to parse inline WIT content")?;
-
-            let wit_source = WitSourceContent::Inline(content.clone());
-            (group, wit_source)
-        }
-    };
-
-    let mut resolve = Resolve::new();
-    let pkg_id = resolve.push(group.main, &group.source_map)?;
-    for pkg in group.nested {
-        resolve.push(pkg, &group.source_map)?;
+        Err(e) => error::to_syn_error(e, &input).to_compile_error().into(),
     }
-
-    // Find the world
-    let world_id = resolve
-        .select_world(pkg_id, Some(&input.world))
-        .with_context(|| format!("World '{}' not found in package", input.world))?;
-
-    let world = &resolve.worlds[world_id];
-    let package = &resolve.packages[world.package.unwrap()];
-
-    // Generate the code
-    let mut generator = CodeGenerator::new(&resolve, package, world, wit_source);
-    let generated = generator.generate()?;
-
-    Ok(generated.into())
 }
 
-fn collect_wit_files(dir: &Path, files: &mut Vec<(String, String)>) -> Result<()> {
-    use std::fs;
-
-    if dir.is_file() {
-        // If it's a single file, read it
-        if let Some(ext) = dir.extension() {
-            if ext == "wit" {
-                let content = fs::read_to_string(dir)
-                    .with_context(|| format!("Failed to read WIT file: {}", dir.display()))?;
-                let filename = dir
-                    .file_name()
-                    .ok_or_else(|| anyhow::anyhow!("Invalid file name"))?
-                    .to_string_lossy()
-                    .to_string();
-                files.push((filename, content));
-            }
-        }
-        return Ok(());
+fn generate_bindings(input: &MacroInput) -> Result<TokenStream> {
+    // Paths are resolved relative to the crate root
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").context("CARGO_MANIFEST_DIR not set")?;
+    let mut opts = Opts::new()
+        .base_dir(manifest_dir)
+        .stubs(input.stubs)
+        .optional_imports(input.optional_imports)
+        .async_mode(input.async_mode)
+        .single_threaded(input.single_threaded)
+        .stub_component(input.stub_component)
+        .resolve(input.resolve)
+        .tracing(input.tracing);
+    if let Some(world) = &input.world {
+        opts = opts.world(world);
     }
-
-    if !dir.is_dir() {
-        return Err(anyhow::anyhow!(
-            "Path is neither a file nor a directory: {}",
-            dir.display()
-        ));
+    for path in &input.paths {
+        opts = opts.path(path);
     }
-
-    // Recursively collect all .wit files
-    for entry in
-        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?
-    {
-        let entry = entry.with_context(|| "Failed to read directory entry")?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            collect_wit_files(&path, files)?;
-        } else if let Some(ext) = path.extension() {
-            if ext == "wit" {
-                let content = fs::read_to_string(&path)
-                    .with_context(|| format!("Failed to read WIT file: {}", path.display()))?;
-                let filename = path
-                    .file_name()
-                    .ok_or_else(|| anyhow::anyhow!("Invalid file name"))?
-                    .to_string_lossy()
-                    .to_string();
-                files.push((filename, content));
-            }
-        }
+    if let Some(lit) = &input.inline {
+        opts = opts.inline(lit.value());
     }
 
-    Ok(())
+    Ok(opts.generate()?.into())
 }
@@ -1,17 +1,33 @@
+use proc_macro2::Span;
 use syn::{
-    braced,
+    braced, bracketed,
+    ext::IdentExt,
     parse::{Parse, ParseStream},
-    Ident, LitStr, Result, Token,
+    punctuated::Punctuated,
+    token, Ident, LitBool, LitStr, Result, Token,
 };
 
 pub struct MacroInput {
-    pub world: String,
-    pub source: WitSource,
-}
-
-pub enum WitSource {
-    Path(String),
-    Inline(String),
+    /// Optional when the main package defines a single world.
+    pub world: Option<String>,
+    /// Directories, `.wit` files or `.wasm` encoded packages. The first entry holds the main
+    /// package unless there is inline WIT, the rest are dependencies.
+    pub paths: Vec<String>,
+    /// Kept as a literal so errors can point at a position inside the string.
+    pub inline: Option<LitStr>,
+    /// Spans of the `world`, `path`, `inline` and `threading` values, errors are reported there.
+    pub world_span: Span,
+    pub path_span: Span,
+    pub inline_span: Span,
+    pub threading_span: Span,
+    /// Options, documented on the `wit_derive_codegen::Opts` methods of the same name.
+    pub stubs: bool,
+    pub optional_imports: bool,
+    pub async_mode: bool,
+    pub single_threaded: bool,
+    pub stub_component: bool,
+    pub resolve: bool,
+    pub tracing: bool,
 }
 
 impl Parse for MacroInput {
@@ -22,23 +38,69 @@ impl Parse for MacroInput {
         let mut world = None;
         let mut path = None;
         let mut inline = None;
+        let mut stubs = false;
+        let mut optional_imports = false;
+        let mut async_mode = false;
+        let mut single_threaded = false;
+        let mut stub_component = false;
+        let mut resolve = false;
+        let mut tracing = false;
+        let mut world_span = input.span();
+        let mut path_span = input.span();
+        let mut inline_span = input.span();
+        let mut threading_span = input.span();
 
         while !content.is_empty() {
-            let key: Ident = content.parse()?;
+            // `async` is a keyword, so keys are parsed as any identifier
+            let key = content.call(Ident::parse_any)?;
             content.parse::<Token![:]>()?;
 
             match key.to_string().as_str() {
                 "world" => {
                     let lit: LitStr = content.parse()?;
+                    world_span = lit.span();
                     world = Some(lit.value());
                 }
                 "path" => {
-                    let lit: LitStr = content.parse()?;
-                    path = Some(lit.value());
+                    path_span = content.span();
+                    path = Some(parse_paths(&content)?);
                 }
                 "inline" => {
                     let lit: LitStr = content.parse()?;
-                    inline = Some(lit.value());
+                    inline_span = lit.span();
+                    inline = Some(lit);
+                }
+                "stubs" => {
+                    stubs = content.parse::<LitBool>()?.value;
+                }
+                "optional_imports" => {
+                    optional_imports = content.parse::<LitBool>()?.value;
+                }
+                "stub_component" => {
+                    stub_component = content.parse::<LitBool>()?.value;
+                }
+                "resolve" => {
+                    resolve = content.parse::<LitBool>()?.value;
+                }
+                "tracing" => {
+                    tracing = content.parse::<LitBool>()?.value;
+                }
+                "async" => {
+                    async_mode = content.parse::<LitBool>()?.value;
+                }
+                "threading" => {
+                    let lit: LitStr = content.parse()?;
+                    threading_span = lit.span();
+                    single_threaded = match lit.value().as_str() {
+                        "single" => true,
+                        "multi" => false,
+                        _ => {
+                            return Err(syn::Error::new(
+                                lit.span(),
+                                "'threading' must be either \"single\" or \"multi\"",
+                            ));
+                        }
+                    };
                 }
                 _ => {
                     return Err(syn::Error::new_spanned(key, "Unknown field"));
@@ -50,27 +112,44 @@ impl Parse for MacroInput {
             }
         }
 
-        let world =
-            world.ok_or_else(|| syn::Error::new(input.span(), "Missing required field 'world'"))?;
+        if path.is_none() && inline.is_none() {
+            return Err(syn::Error::new(
+                input.span(),
+                "Must specify a 'path' or 'inline' field, or both",
+            ));
+        }
 
-        // Ensure exactly one of path or inline is provided
-        let source = match (path, inline) {
-            (Some(path), None) => WitSource::Path(path),
-            (None, Some(inline)) => WitSource::Inline(inline),
-            (Some(_), Some(_)) => {
-                return Err(syn::Error::new(
-                    input.span(),
-                    "Cannot specify both 'path' and 'inline' fields",
-                ));
-            }
-            (None, None) => {
-                return Err(syn::Error::new(
-                    input.span(),
-                    "Must specify either 'path' or 'inline' field",
-                ));
-            }
-        };
+        Ok(MacroInput {
+            world,
+            paths: path.unwrap_or_default(),
+            inline,
+            world_span,
+            path_span,
+            inline_span,
+            threading_span,
+            stubs,
+            optional_imports,
+            async_mode,
+            single_threaded,
+            stub_component,
+            resolve,
+            tracing,
+        })
+    }
+}
 
-        Ok(MacroInput { world, source })
+/// Parses either a single string literal or a bracketed list of them.
+fn parse_paths(input: ParseStream) -> Result<Vec<String>> {
+    if input.peek(token::Bracket) {
+        let list;
+        bracketed!(list in input);
+        let lits = Punctuated::<LitStr, Token![,]>::parse_terminated(&list)?;
+        if lits.is_empty() {
+            return Err(syn::Error::new(list.span(), "'path' list must not be empty"));
+        }
+        Ok(lits.iter().map(LitStr::value).collect())
+    } else {
+        let lit: LitStr = input.parse()?;
+        Ok(vec![lit.value()])
     }
 }